@@ -1,9 +1,15 @@
+use crate::database::{date, Params};
 use anyhow::{anyhow, bail, Result};
 use chrono_tz::Tz;
 use getopts::Options;
 use once_cell::sync::OnceCell;
+use rust_stemmers::Algorithm;
 use serde::Deserialize;
-use std::{fs, net::SocketAddr, path::Path};
+use std::{
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
 
 static CONFIG: OnceCell<Config> = OnceCell::new();
 
@@ -13,6 +19,17 @@ pub struct Config {
     pub git: Git,
     pub site: Site,
     pub url_patterns: UrlPatterns,
+    #[serde(default)]
+    pub search: Search,
+    pub theme: Option<Theme>,
+    #[serde(default)]
+    pub highlight: Highlight,
+    #[serde(default)]
+    pub content: Content,
+    #[serde(default)]
+    pub markdown: Markdown,
+    #[serde(default)]
+    pub images: Images,
 }
 
 #[derive(Debug, Deserialize)]
@@ -39,6 +56,45 @@ pub struct Site {
     pub homepage: String,
     pub list_posts_count: usize,
     pub list_from_old_to_new: bool,
+    pub related_posts_count: Option<usize>,
+    pub date_format: Option<String>,
+    #[serde(default)]
+    pub language: Language,
+    #[serde(default)]
+    pub params: Params,
+    #[serde(default)]
+    pub summary: SummaryMode,
+    pub summary_length: Option<usize>,
+    #[serde(default = "Site::default_words_per_minute")]
+    pub words_per_minute: usize,
+}
+
+impl Site {
+    fn default_words_per_minute() -> usize {
+        200
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryMode {
+    #[default]
+    Full,
+    FirstParagraph,
+    Words,
+    Characters,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    En,
+    De,
+    Es,
+    Fr,
+    Ja,
+    Zh,
 }
 
 #[derive(Debug, Deserialize)]
@@ -52,6 +108,144 @@ pub struct UrlPatterns {
     pub search: String,
 }
 
+#[derive(Debug, Default, Deserialize)]
+pub struct Search {
+    pub stemming: Option<Algorithm>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Content {
+    #[serde(default = "Content::default_formats")]
+    pub formats: Vec<ContentFormat>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum ContentFormat {
+    #[serde(rename = "md")]
+    Markdown,
+    #[serde(rename = "html")]
+    Html,
+    #[serde(rename = "adoc")]
+    AsciiDoc,
+    #[serde(rename = "txt")]
+    PlainText,
+}
+
+impl Content {
+    fn default_formats() -> Vec<ContentFormat> {
+        vec![ContentFormat::Markdown]
+    }
+}
+
+impl Default for Content {
+    fn default() -> Self {
+        Self {
+            formats: Self::default_formats(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Markdown {
+    #[serde(default)]
+    pub heading_anchors: bool,
+    #[serde(default = "Markdown::default_toc_depth")]
+    pub toc_depth: usize,
+    #[serde(default = "Markdown::default_math")]
+    pub math: bool,
+}
+
+impl Markdown {
+    fn default_toc_depth() -> usize {
+        3
+    }
+
+    fn default_math() -> bool {
+        true
+    }
+}
+
+impl Default for Markdown {
+    fn default() -> Self {
+        Self {
+            heading_anchors: false,
+            toc_depth: Self::default_toc_depth(),
+            math: Self::default_math(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Highlight {
+    #[serde(default)]
+    pub theme: HighlightTheme,
+    #[serde(default)]
+    pub inline_styles: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Images {
+    #[serde(default = "Images::default_widths")]
+    pub widths: Vec<u32>,
+    #[serde(default = "Images::default_webp")]
+    pub webp: bool,
+    #[serde(default = "Images::default_cache_dir")]
+    pub cache_dir: PathBuf,
+    #[serde(default = "Images::default_prefix")]
+    pub prefix: String,
+}
+
+impl Images {
+    fn default_widths() -> Vec<u32> {
+        vec![480, 960, 1600]
+    }
+
+    fn default_webp() -> bool {
+        true
+    }
+
+    fn default_cache_dir() -> PathBuf {
+        std::env::temp_dir().join("hummingbird-images")
+    }
+
+    fn default_prefix() -> String {
+        String::from("/_images/")
+    }
+}
+
+impl Default for Images {
+    fn default() -> Self {
+        Self {
+            widths: Self::default_widths(),
+            webp: Self::default_webp(),
+            cache_dir: Self::default_cache_dir(),
+            prefix: Self::default_prefix(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HighlightTheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Theme {
+    pub path: Option<PathBuf>,
+    pub git: Option<Git>,
+    #[serde(default = "Theme::default_static_prefix")]
+    pub static_prefix: String,
+}
+
+impl Theme {
+    fn default_static_prefix() -> String {
+        String::from("/theme/")
+    }
+}
+
 impl Config {
     fn from_file(config_file: String) -> Result<Config> {
         let config = fs::read_to_string(Path::new(&config_file))?;
@@ -59,13 +253,63 @@ impl Config {
         Ok(config)
     }
 
-    fn process(&mut self) {
+    fn process(&mut self) -> Result<()> {
         self.site.url = self.site.url.trim_end_matches('/').to_owned();
+
+        if let Some(date_format) = &self.site.date_format {
+            date::check_pattern(date_format)?;
+        }
+
+        if let Some(theme) = &mut self.theme {
+            if theme.path.is_some() == theme.git.is_some() {
+                bail!("Exactly one of `path` and `git` should be set for the theme");
+            }
+
+            if !theme.static_prefix.starts_with('/') {
+                theme.static_prefix.insert(0, '/');
+            }
+            if !theme.static_prefix.ends_with('/') {
+                theme.static_prefix.push('/');
+            }
+        }
+
+        if !self.images.prefix.starts_with('/') {
+            self.images.prefix.insert(0, '/');
+        }
+        if !self.images.prefix.ends_with('/') {
+            self.images.prefix.push('/');
+        }
+        if self.site.summary_length == Some(0) {
+            bail!("`summary_length` should be greater than 0");
+        }
+
+        if self.site.words_per_minute == 0 {
+            bail!("`words_per_minute` should be greater than 0");
+        }
+
+        self.images.widths.sort_unstable();
+        self.images.widths.dedup();
+
+        Ok(())
     }
 
     pub fn read() -> &'static Self {
         CONFIG.get().unwrap()
     }
+
+    #[cfg(test)]
+    pub fn init_for_test() {
+        CONFIG.get_or_init(|| {
+            let mut config: Config = toml::from_str(include_str!("../hummingbird.conf")).unwrap();
+            config.process().unwrap();
+            config
+        });
+    }
+}
+
+pub enum Command {
+    Serve,
+    Lint { json: bool },
 }
 
 pub struct ConfigBuilder<'cfg> {
@@ -78,6 +322,7 @@ impl<'cfg> ConfigBuilder<'cfg> {
     pub fn new() -> Self {
         let mut opts = Options::new();
         opts.optopt("c", "config-file", "config file path", "CONFIG");
+        opts.optflag("", "json", "print the lint report as JSON");
         opts.optflag("h", "help", "print the help menu");
 
         ConfigBuilder {
@@ -87,18 +332,24 @@ impl<'cfg> ConfigBuilder<'cfg> {
     }
 
     pub fn get_usage(&self) -> String {
-        self.opts
-            .usage(&format!("Usage: {} [options]", self.program.unwrap()))
+        self.opts.usage(&format!(
+            "Usage: {} [options] [lint]",
+            self.program.unwrap()
+        ))
     }
 
-    pub fn parse(&mut self, args: &'cfg [String]) -> Result<()> {
+    pub fn parse(&mut self, args: &'cfg [String]) -> Result<Command> {
         self.program = Some(&args[0]);
 
         let matches = self.opts.parse(&args[1..])?;
 
-        if !matches.free.is_empty() {
-            bail!("unexpected arguments: {}", matches.free.join(", "));
-        }
+        let command = match matches.free.as_slice() {
+            [] => Command::Serve,
+            [command] if command == "lint" => Command::Lint {
+                json: matches.opt_present("json"),
+            },
+            free => bail!("unexpected arguments: {}", free.join(", ")),
+        };
 
         if matches.opt_present("h") {
             bail!("");
@@ -109,10 +360,10 @@ impl<'cfg> ConfigBuilder<'cfg> {
             .ok_or_else(|| anyhow!("No config file specificed"))?;
 
         let mut config = Config::from_file(config_file)?;
-        config.process();
+        config.process()?;
 
         CONFIG.set(config).unwrap();
 
-        Ok(())
+        Ok(command)
     }
 }
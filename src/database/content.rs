@@ -0,0 +1,144 @@
+use super::{template::format, Page, Post};
+use chrono::DateTime;
+use chrono_tz::Tz;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ContentType {
+    Post,
+    Page,
+}
+
+impl ContentType {
+    pub fn parse(str: &str) -> Option<Self> {
+        match str {
+            "post" => Some(Self::Post),
+            "page" => Some(Self::Page),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Post => "post",
+            Self::Page => "page",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ContentId {
+    Post(usize),
+    Page(usize),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Content<'c> {
+    Post(&'c Post),
+    Page(&'c Page),
+}
+
+impl<'c> Content<'c> {
+    pub fn content_type(&self) -> ContentType {
+        match self {
+            Self::Post(_) => ContentType::Post,
+            Self::Page(_) => ContentType::Page,
+        }
+    }
+
+    pub fn title(&self) -> &'c str {
+        match self {
+            Self::Post(post) => &post.title,
+            Self::Page(page) => &page.title,
+        }
+    }
+
+    pub fn url(&self) -> &'c str {
+        match self {
+            Self::Post(post) => &post.url,
+            Self::Page(page) => &page.url,
+        }
+    }
+
+    pub fn content(&self) -> &'c str {
+        match self {
+            Self::Post(post) => &post.content,
+            Self::Page(page) => &page.content,
+        }
+    }
+
+    pub fn path(&self) -> &'c Path {
+        match self {
+            Self::Post(post) => &post.path,
+            Self::Page(page) => &page.path,
+        }
+    }
+
+    pub fn text(&self) -> String {
+        format::from_path(self.path())
+            .map_or_else(String::new, |format| format.to_text(self.content()))
+    }
+
+    pub fn html(&self) -> &'c str {
+        match self {
+            Self::Post(post) => &post.html,
+            Self::Page(page) => &page.html,
+        }
+    }
+
+    pub fn summary_html(&self) -> &'c str {
+        match self {
+            Self::Post(post) => &post.summary_html,
+            Self::Page(page) => &page.summary_html,
+        }
+    }
+
+    pub fn has_more(&self) -> bool {
+        match self {
+            Self::Post(post) => post.has_more,
+            Self::Page(page) => page.has_more,
+        }
+    }
+
+    pub fn word_count(&self) -> usize {
+        match self {
+            Self::Post(post) => post.word_count,
+            Self::Page(page) => page.word_count,
+        }
+    }
+
+    pub fn reading_time(&self) -> usize {
+        match self {
+            Self::Post(post) => post.reading_time,
+            Self::Page(page) => page.reading_time,
+        }
+    }
+
+    pub fn author(&self) -> Option<&'c str> {
+        match self {
+            Self::Post(post) => post.author.as_deref(),
+            Self::Page(page) => page.author.as_deref(),
+        }
+    }
+
+    pub fn tags(&self) -> &'c [String] {
+        match self {
+            Self::Post(post) => &post.tags,
+            Self::Page(_) => &[],
+        }
+    }
+
+    pub fn create_time(&self) -> &'c DateTime<Tz> {
+        match self {
+            Self::Post(post) => &post.create_time,
+            Self::Page(page) => &page.create_time,
+        }
+    }
+
+    pub fn modify_time(&self) -> &'c DateTime<Tz> {
+        match self {
+            Self::Post(post) => &post.modify_time,
+            Self::Page(page) => &page.modify_time,
+        }
+    }
+}
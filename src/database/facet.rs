@@ -0,0 +1,82 @@
+use super::Content;
+use chrono::Datelike;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FacetKind {
+    Author,
+    Year,
+    Month,
+    Tag,
+}
+
+impl FacetKind {
+    pub fn parse(str: &str) -> Option<Self> {
+        match str {
+            "author" => Some(Self::Author),
+            "year" => Some(Self::Year),
+            "month" => Some(Self::Month),
+            "tag" => Some(Self::Tag),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Author => "author",
+            Self::Year => "year",
+            Self::Month => "month",
+            Self::Tag => "tag",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Facets<'d> {
+    pub authors: Vec<(&'d str, usize)>,
+    pub years: Vec<(i32, usize)>,
+    pub months: Vec<((i32, u32), usize)>,
+    pub tags: Vec<(&'d str, usize)>,
+}
+
+impl<'d> Facets<'d> {
+    pub fn from_contents(contents: &[Content<'d>]) -> Self {
+        let mut authors = HashMap::new();
+        let mut years = HashMap::new();
+        let mut months = HashMap::new();
+        let mut tags = HashMap::new();
+
+        for content in contents {
+            if let Some(author) = content.author() {
+                *authors.entry(author).or_insert(0) += 1;
+            }
+
+            let create_time = content.create_time();
+            *years.entry(create_time.year()).or_insert(0) += 1;
+            *months
+                .entry((create_time.year(), create_time.month()))
+                .or_insert(0) += 1;
+
+            for tag in content.tags() {
+                *tags.entry(tag.as_str()).or_insert(0) += 1;
+            }
+        }
+
+        let mut authors = authors.into_iter().collect::<Vec<_>>();
+        let mut years = years.into_iter().collect::<Vec<_>>();
+        let mut months = months.into_iter().collect::<Vec<_>>();
+        let mut tags = tags.into_iter().collect::<Vec<_>>();
+
+        authors.sort_unstable_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        years.sort_unstable_by(|(a, _), (b, _)| b.cmp(a));
+        months.sort_unstable_by(|(a, _), (b, _)| b.cmp(a));
+        tags.sort_unstable_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+
+        Self {
+            authors,
+            years,
+            months,
+            tags,
+        }
+    }
+}
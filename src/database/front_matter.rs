@@ -0,0 +1,68 @@
+use super::Params;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FrontMatter {
+    pub series: Option<String>,
+    pub tags: Vec<String>,
+    pub layout: Option<String>,
+    pub summary: Option<String>,
+    pub params: Params,
+    #[serde(skip)]
+    pub directory: Option<String>,
+}
+
+impl FrontMatter {
+    const DELIMITER: &'static str = "+++";
+
+    pub fn extract(content: String, path: &Path) -> Result<(Self, String)> {
+        let (mut front_matter, body) = Self::split(content, path)?;
+
+        if path.iter().count() > 2 {
+            front_matter.directory = path
+                .iter()
+                .nth(1)
+                .map(|directory| directory.to_string_lossy().into_owned());
+        }
+
+        Ok((front_matter, body))
+    }
+
+    fn split(content: String, path: &Path) -> Result<(Self, String)> {
+        let mut lines = content.split_inclusive('\n');
+
+        match lines.next() {
+            Some(line) if line.trim_end() == Self::DELIMITER => {}
+            _ => return Ok((Self::default(), content)),
+        }
+
+        let start = content.find('\n').unwrap() + 1;
+        let mut end = start;
+
+        for line in lines {
+            if line.trim_end() == Self::DELIMITER {
+                let front_matter = toml::from_str(&content[start..end]).map_err(|err| {
+                    anyhow!(
+                        "Failed to parse front matter of {}: {}",
+                        path.display(),
+                        err
+                    )
+                })?;
+                let body = content[end + line.len()..].to_owned();
+
+                return Ok((front_matter, body));
+            }
+
+            end += line.len();
+        }
+
+        Err(anyhow!(
+            "Unclosed front matter in {}: missing `{}`",
+            path.display(),
+            Self::DELIMITER
+        ))
+    }
+}
@@ -1,60 +1,72 @@
-use super::Template;
-use crate::Config;
+use super::{ContentType, Images, Template};
+use crate::{config::Git, Config};
 use anyhow::Result;
 use git2::{
-    build::RepoBuilder, Cred, DiffFindOptions, FetchOptions, ProxyOptions, RemoteCallbacks,
-    Repository, ResetType,
+    build::RepoBuilder, Cred, DiffFindOptions, FetchOptions, ObjectType, Oid, ProxyOptions,
+    RemoteCallbacks, Repository, ResetType, TreeWalkMode, TreeWalkResult,
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
 };
-use std::{collections::HashMap, path::PathBuf};
 use tempfile::TempDir;
 
 pub struct Repo {
     pub repo: Repository,
     pub tempdir: TempDir,
+    git: &'static Git,
 }
 
 impl Repo {
     pub fn init() -> Result<Self> {
+        Self::open_remote(&Config::read().git)
+    }
+
+    pub fn open_remote(git: &'static Git) -> Result<Self> {
         let mut builder = RepoBuilder::new();
-        builder.fetch_options(Self::get_fetch_options());
+        builder.fetch_options(Self::get_fetch_options(git));
 
         let tempdir = TempDir::new()?;
 
-        let repo = builder.clone(&Config::read().git.repository, tempdir.path())?;
+        let repo = builder.clone(&git.repository, tempdir.path())?;
 
-        Ok(Self { repo, tempdir })
+        Ok(Self { repo, tempdir, git })
     }
 
-    pub async fn parse_repo(&mut self) -> Result<ParsedGitRepo> {
+    pub async fn parse_repo(&mut self, theme: Option<&Path>) -> Result<ParsedGitRepo> {
         self.fetch()?;
 
         let mut pages_git_info = HashMap::new();
         let mut posts_git_info = HashMap::new();
 
         self.get_git_info(&mut pages_git_info, &mut posts_git_info)?;
+        let images_git_info = self.get_image_blobs()?;
 
-        let template_path = self.tempdir.path().join("template/");
-        let template = Template::from_directory(&template_path).await?;
+        let mut template_paths = vec![self.tempdir.path().join("template/")];
+        if let Some(theme) = theme {
+            template_paths.push(theme.join("template/"));
+        }
+        let template = Template::from_directories(&template_paths).await;
 
         Ok(ParsedGitRepo {
             template,
             pages_git_info,
             posts_git_info,
+            images_git_info,
         })
     }
 
-    fn fetch(&mut self) -> Result<()> {
+    pub fn fetch(&mut self) -> Result<()> {
         let mut origin_remote = self.repo.find_remote("origin")?;
 
         origin_remote.fetch(
-            &[&Config::read().git.branch],
-            Some(&mut Self::get_fetch_options()),
+            &[&self.git.branch],
+            Some(&mut Self::get_fetch_options(self.git)),
             None,
         )?;
-        let oid = self.repo.refname_to_id(&format!(
-            "refs/remotes/origin/{}",
-            &Config::read().git.branch
-        ))?;
+        let oid = self
+            .repo
+            .refname_to_id(&format!("refs/remotes/origin/{}", &self.git.branch))?;
 
         let object = self.repo.find_object(oid, None)?;
         self.repo.reset(&object, ResetType::Hard, None)?;
@@ -62,6 +74,25 @@ impl Repo {
         Ok(())
     }
 
+    fn get_image_blobs(&self) -> Result<HashMap<PathBuf, Oid>> {
+        let mut blobs = HashMap::new();
+        let tree = self.repo.head()?.peel_to_tree()?;
+
+        tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+            if let (Some(ObjectType::Blob), Some(name)) = (entry.kind(), entry.name()) {
+                let path = Path::new(dir).join(name);
+
+                if Images::is_image(&path) {
+                    blobs.insert(path, entry.id());
+                }
+            }
+
+            TreeWalkResult::Ok
+        })?;
+
+        Ok(blobs)
+    }
+
     fn get_git_info(
         &self,
         pages_git_info: &mut HashMap<PathBuf, GitFileInfo>,
@@ -227,19 +258,16 @@ impl Repo {
         Ok(())
     }
 
-    fn get_fetch_options<'repo>() -> FetchOptions<'repo> {
+    fn get_fetch_options<'repo>(git: &'static Git) -> FetchOptions<'repo> {
         let mut fetch_options = FetchOptions::new();
 
-        if let Some(proxy_url) = Config::read().git.proxy.as_ref() {
+        if let Some(proxy_url) = git.proxy.as_ref() {
             let mut proxy_option = ProxyOptions::new();
             proxy_option.url(proxy_url);
             fetch_options.proxy_options(proxy_option);
         }
 
-        if let (Some(username), Some(password)) = (
-            Config::read().git.user.as_ref(),
-            Config::read().git.password.as_ref(),
-        ) {
+        if let (Some(username), Some(password)) = (git.user.as_ref(), git.password.as_ref()) {
             let mut remote_callbacks = RemoteCallbacks::new();
             remote_callbacks
                 .credentials(move |_, _, _| Cred::userpass_plaintext(username, password));
@@ -254,11 +282,12 @@ impl Repo {
 unsafe impl Send for Repo {}
 unsafe impl Sync for Repo {}
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct ParsedGitRepo {
-    pub template: Template,
+    pub template: Result<Template>,
     pub posts_git_info: HashMap<PathBuf, GitFileInfo>,
     pub pages_git_info: HashMap<PathBuf, GitFileInfo>,
+    pub images_git_info: HashMap<PathBuf, Oid>,
 }
 
 #[derive(Debug, Clone)]
@@ -283,12 +312,6 @@ impl GitFileInfo {
     }
 }
 
-#[derive(Clone)]
-enum ContentType {
-    Page,
-    Post,
-}
-
 #[derive(Clone)]
 enum FileStatus {
     Created(ContentType),
@@ -0,0 +1,185 @@
+use super::{Problem, ProblemKind};
+use crate::Config;
+use anyhow::{Error, Result};
+use git2::Oid;
+use image::{imageops::FilterType, DynamicImage, ImageFormat};
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use tokio::task;
+
+#[derive(Debug, Default)]
+pub struct Images {
+    data: HashMap<String, Image>,
+}
+
+#[derive(Debug)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub srcset: Option<String>,
+    pub webp_srcset: Option<String>,
+}
+
+impl Images {
+    const EXTENSIONS: [&'static str; 5] = ["png", "jpg", "jpeg", "gif", "webp"];
+
+    pub fn is_image(path: &Path) -> bool {
+        path.extension()
+            .and_then(OsStr::to_str)
+            .is_some_and(|ext| Self::EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+    }
+
+    pub async fn from_git_blobs(
+        blobs: HashMap<PathBuf, Oid>,
+        tempdir: &Path,
+        problems: &Mutex<Vec<Problem>>,
+    ) -> Result<Self> {
+        fs::create_dir_all(&Config::read().images.cache_dir)?;
+
+        let tasks = blobs
+            .into_iter()
+            .map(|(path, oid)| {
+                let url = format!("/{}", path.to_string_lossy());
+                let file = tempdir.join(&path);
+                let task = task::spawn_blocking({
+                    let url = url.clone();
+                    move || Image::process(&file, &url, oid)
+                });
+                (path, url, task)
+            })
+            .collect::<Vec<_>>();
+
+        let mut data = HashMap::new();
+        for (path, url, task) in tasks {
+            match task.await.map_err(Error::from).and_then(|image| image) {
+                Ok(image) => {
+                    data.insert(url, image);
+                }
+                Err(err) => problems.lock().unwrap().push(Problem::new(
+                    &path,
+                    ProblemKind::InvalidImage,
+                    format!("Invalid image: {}", err),
+                )),
+            }
+        }
+
+        Ok(Self { data })
+    }
+
+    pub fn get(&self, src: &str) -> Option<&Image> {
+        let src = src.strip_prefix(&Config::read().site.url).unwrap_or(src);
+        let src = src.split(['?', '#']).next().unwrap();
+        self.data.get(src)
+    }
+}
+
+impl Image {
+    fn process(path: &Path, url: &str, oid: Oid) -> Result<Self> {
+        let (width, height) = image::image_dimensions(path)?;
+
+        let format = match ImageFormat::from_path(path)? {
+            format @ (ImageFormat::Png | ImageFormat::Jpeg) => format,
+            _ => {
+                return Ok(Self {
+                    width,
+                    height,
+                    srcset: None,
+                    webp_srcset: None,
+                })
+            }
+        };
+
+        let config = &Config::read().images;
+        let ext = path
+            .extension()
+            .unwrap()
+            .to_string_lossy()
+            .to_ascii_lowercase();
+        let widths = config
+            .widths
+            .iter()
+            .copied()
+            .filter(|variant_width| *variant_width < width)
+            .collect::<Vec<_>>();
+
+        let mut variants = widths
+            .iter()
+            .map(|variant_width| (*variant_width, format, ext.as_str()))
+            .collect::<Vec<_>>();
+        if config.webp {
+            variants.extend(
+                widths
+                    .iter()
+                    .chain([width].iter())
+                    .map(|variant_width| (*variant_width, ImageFormat::WebP, "webp")),
+            );
+        }
+
+        let mut image: Option<DynamicImage> = None;
+
+        for (variant_width, format, ext) in &variants {
+            let file = config
+                .cache_dir
+                .join(format!("{}-{}.{}", oid, variant_width, ext));
+
+            if file.exists() {
+                continue;
+            }
+
+            let original = match &image {
+                Some(image) => image,
+                None => image.insert(image::open(path)?),
+            };
+
+            let variant_height =
+                ((height as u64 * *variant_width as u64) / width as u64).max(1) as u32;
+            let variant = match variant_width {
+                variant_width if *variant_width == width => original.clone(),
+                variant_width => {
+                    original.resize_exact(*variant_width, variant_height, FilterType::Lanczos3)
+                }
+            };
+            let variant = match format {
+                ImageFormat::Jpeg => DynamicImage::ImageRgb8(variant.to_rgb8()),
+                _ => DynamicImage::ImageRgba8(variant.to_rgba8()),
+            };
+
+            let temp_file = file.with_extension(format!("{}.tmp", ext));
+            variant.save_with_format(&temp_file, *format)?;
+            fs::rename(temp_file, file)?;
+        }
+
+        let site_url = &Config::read().site.url;
+        let srcset = |ext: &str, original: Option<&str>| {
+            let mut srcset = widths
+                .iter()
+                .map(|variant_width| {
+                    format!(
+                        "{}{}{}-{}.{} {}w",
+                        site_url, config.prefix, oid, variant_width, ext, variant_width
+                    )
+                })
+                .collect::<Vec<_>>();
+            match original {
+                Some(original) => srcset.push(format!("{}{} {}w", site_url, original, width)),
+                None => srcset.push(format!(
+                    "{}{}{}-{}.{} {}w",
+                    site_url, config.prefix, oid, width, ext, width
+                )),
+            }
+            srcset.join(", ")
+        };
+
+        Ok(Self {
+            width,
+            height,
+            srcset: (!widths.is_empty()).then(|| srcset(&ext, Some(url))),
+            webp_srcset: config.webp.then(|| srcset("webp", None)),
+        })
+    }
+}
@@ -0,0 +1,130 @@
+use super::Database;
+use crate::{config::SummaryMode, Config};
+use regex::Regex;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    path::Path,
+};
+
+#[derive(Debug, Default, Serialize)]
+pub struct LintReport {
+    pub problems: Vec<Problem>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Problem {
+    pub file: String,
+    pub kind: ProblemKind,
+    pub message: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemKind {
+    BrokenLink,
+    MissingImage,
+    InvalidImage,
+    InvalidShortcode,
+    DuplicateUrl,
+    MissingMore,
+    MalformedMore,
+    EmptyTitle,
+}
+
+impl Problem {
+    pub fn new(file: &Path, kind: ProblemKind, message: String) -> Self {
+        Self {
+            file: file.to_string_lossy().into_owned(),
+            kind,
+            message,
+        }
+    }
+}
+
+impl Database {
+    pub fn lint(&self) -> LintReport {
+        let mut problems = self.problems.clone();
+        let mut urls = BTreeMap::new();
+        let more_regex = Regex::new(r"(?i)<!-+\s*more\b[^>\n]*>?").unwrap();
+
+        let contents = self
+            .posts
+            .data
+            .iter()
+            .map(|post| (&post.path, &post.title, &post.url))
+            .chain(
+                self.pages
+                    .data
+                    .iter()
+                    .map(|page| (&page.path, &page.title, &page.url)),
+            );
+
+        for (path, title, url) in contents {
+            urls.entry(url).or_insert_with(Vec::new).push(path);
+
+            if title.trim().is_empty() {
+                problems.push(Problem::new(
+                    path,
+                    ProblemKind::EmptyTitle,
+                    String::from("The title is empty"),
+                ));
+            }
+        }
+
+        for (url, paths) in urls.into_iter().filter(|(_, paths)| paths.len() > 1) {
+            for path in &paths {
+                let others = paths
+                    .iter()
+                    .filter(|other| other != &path)
+                    .map(|other| other.to_string_lossy())
+                    .collect::<Vec<_>>();
+
+                problems.push(Problem::new(
+                    path,
+                    ProblemKind::DuplicateUrl,
+                    format!("The URL {} is also used by {}", url, others.join(", ")),
+                ));
+            }
+        }
+
+        for post in &self.posts.data {
+            let malformed = more_regex
+                .find_iter(&post.content)
+                .filter(|found| found.as_str() != "<!--more-->")
+                .map(|found| found.as_str().to_owned())
+                .collect::<Vec<_>>();
+
+            if !malformed.is_empty() {
+                for found in malformed {
+                    problems.push(Problem::new(
+                        &post.path,
+                        ProblemKind::MalformedMore,
+                        format!("`{}` should be `<!--more-->`", found),
+                    ));
+                }
+            } else if Config::read().site.summary == SummaryMode::Full
+                && !post.has_more
+                && !post.content.contains("<!--more-->")
+            {
+                problems.push(Problem::new(
+                    &post.path,
+                    ProblemKind::MissingMore,
+                    String::from("No `<!--more-->`, the whole post is shown in lists"),
+                ));
+            }
+        }
+
+        problems.sort();
+        problems.dedup();
+
+        LintReport { problems }
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.file, self.message)
+    }
+}
@@ -0,0 +1,174 @@
+use super::Pages;
+use crate::Config;
+use anyhow::{anyhow, bail, Result};
+use serde::Deserialize;
+use std::{collections::HashMap, io::ErrorKind, path::Path};
+use tokio::fs;
+
+#[derive(Debug)]
+pub struct Menus {
+    pub menus: HashMap<String, Vec<MenuItem>>,
+}
+
+impl Menus {
+    pub async fn from_file(path: &Path, pages: &Pages) -> Result<Self> {
+        let file = match fs::read_to_string(path).await {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                return Ok(Self {
+                    menus: HashMap::new(),
+                })
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let raw_menus: HashMap<String, Vec<RawMenuItem>> =
+            toml::from_str(&file).map_err(|err| anyhow!("Failed to parse menu.toml: {}", err))?;
+
+        let menus = raw_menus
+            .into_iter()
+            .map(|(name, items)| {
+                let items = items
+                    .into_iter()
+                    .map(|item| MenuItem::from_raw(item, &name, pages))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok((name, items))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+
+        Ok(Self { menus })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&[MenuItem]> {
+        self.menus.get(name).map(|items| items.as_slice())
+    }
+}
+
+#[derive(Debug)]
+pub struct MenuItem {
+    pub title: String,
+    pub url: String,
+    pub path: Option<String>,
+}
+
+impl MenuItem {
+    fn from_raw(item: RawMenuItem, menu: &str, pages: &Pages) -> Result<Self> {
+        let site_url = &Config::read().site.url;
+
+        let (title, path) = match (item.page, item.link, item.route) {
+            (Some(slug), None, None) => {
+                let page = pages
+                    .data
+                    .iter()
+                    .find(|page| page.title == slug)
+                    .ok_or_else(|| anyhow!("Menu `{}`: unknown page `{}`", menu, slug))?;
+
+                let title = item.title.unwrap_or_else(|| page.title.clone());
+                let path = page.url[site_url.len()..].to_owned();
+
+                (title, Some(path))
+            }
+            (None, Some(link), None) => {
+                let title = item
+                    .title
+                    .ok_or_else(|| anyhow!("Menu `{}`: link `{}` has no title", menu, link))?;
+
+                if link.starts_with('/') {
+                    (title, Some(link))
+                } else {
+                    return Ok(Self {
+                        title,
+                        url: link,
+                        path: None,
+                    });
+                }
+            }
+            (None, None, Some(route)) => {
+                let path = route.to_path(item.year, item.month, menu)?;
+                let title = item
+                    .title
+                    .unwrap_or_else(|| route.default_title().to_owned());
+
+                (title, Some(path))
+            }
+            _ => bail!(
+                "Menu `{}`: every item must have exactly one of `page`, `link` or `route`",
+                menu
+            ),
+        };
+
+        let url = format!("{}{}", site_url, path.as_deref().unwrap());
+
+        Ok(Self { title, url, path })
+    }
+
+    pub fn is_active(&self, path: &str) -> bool {
+        let item_path = match &self.path {
+            Some(item_path) => item_path.trim_end_matches('/'),
+            None => return false,
+        };
+
+        let path = if path == "/" {
+            &Config::read().site.homepage
+        } else {
+            path
+        };
+
+        item_path == path.trim_end_matches('/')
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawMenuItem {
+    title: Option<String>,
+    page: Option<String>,
+    link: Option<String>,
+    route: Option<MenuRoute>,
+    year: Option<i32>,
+    month: Option<u32>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum MenuRoute {
+    Index,
+    Archive,
+    Search,
+}
+
+impl MenuRoute {
+    fn to_path(self, year: Option<i32>, month: Option<u32>, menu: &str) -> Result<String> {
+        let url_patterns = &Config::read().url_patterns;
+
+        match self {
+            Self::Index => Ok(url_patterns.index.to_owned()),
+            Self::Search => Ok(url_patterns.search.to_owned()),
+            Self::Archive => {
+                let year = year
+                    .ok_or_else(|| anyhow!("Menu `{}`: archive route requires a `year`", menu))?;
+
+                let path = match month {
+                    Some(month) => url_patterns
+                        .archive
+                        .replace(":year", &year.to_string())
+                        .replace(":month", &month.to_string()),
+                    None => url_patterns
+                        .archive
+                        .replace("/:month", "")
+                        .replace(":year", &year.to_string()),
+                };
+
+                Ok(path)
+            }
+        }
+    }
+
+    fn default_title(self) -> &'static str {
+        match self {
+            Self::Index => "Home",
+            Self::Archive => "Archive",
+            Self::Search => "Search",
+        }
+    }
+}
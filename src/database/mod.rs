@@ -1,74 +1,372 @@
-use self::git::ParsedGitRepo;
+use self::{
+    git::{GitFileInfo, ParsedGitRepo},
+    search_index::SearchIndex,
+    template::markdown::Renderer,
+    text::{DocumentTokens, Tokenizer},
+};
 use crate::{Config, RouteTable};
 use anyhow::{anyhow, Error, Result};
-use chrono::{DateTime, TimeZone};
+use chrono::{DateTime, NaiveDate, TimeZone};
 use chrono_tz::Tz;
+use git2::Oid;
 use once_cell::sync::OnceCell;
-use std::fmt::{self, Display, Formatter};
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    path::PathBuf,
+    sync::Mutex,
+};
 use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 pub use self::{
     author::Authors,
+    content::{Content, ContentId, ContentType},
+    facet::{FacetKind, Facets},
     git::Repo,
+    images::Images,
+    lint::{LintReport, Problem, ProblemKind},
+    menu::{MenuItem, Menus},
     page::{Page, Pages},
-    post::{Post, PostFilter, Posts},
-    template::Template,
+    params::Params,
+    post::{Post, Posts},
+    search_query::{SearchFilter, SearchQuery, SearchSort},
+    template::{date, format, Template},
+    theme::Theme,
 };
 
 mod author;
+mod content;
+mod facet;
+mod front_matter;
 mod git;
+mod images;
+mod lint;
+mod menu;
 mod page;
+mod params;
 mod post;
+mod related;
+mod search_index;
+mod search_query;
 mod template;
+mod text;
+mod theme;
 
 static DATABASE: OnceCell<RwLock<Database>> = OnceCell::new();
 
 pub struct Database {
     pub repo: Repo,
+    pub theme: Option<Theme>,
     pub template: Template,
     pub posts: Posts,
     pub pages: Pages,
     pub authors: Authors,
+    pub menus: Menus,
+    pub params: Params,
+    pub index: SearchIndex,
+    pub problems: Vec<Problem>,
+}
+
+struct Contents {
+    posts: Posts,
+    pages: Pages,
+    authors: Authors,
+    menus: Menus,
+    params: Params,
+    index: SearchIndex,
+    problems: Vec<Problem>,
 }
 
 impl Database {
     pub async fn init() -> Result<Database> {
-        let mut repo = Repo::init()?;
+        Self::open(Repo::init()?, Theme::init()?).await
+    }
 
+    async fn open(mut repo: Repo, theme: Option<Theme>) -> Result<Database> {
         let ParsedGitRepo {
             template,
             pages_git_info,
             posts_git_info,
-        } = repo.parse_repo().await?;
+            images_git_info,
+        } = repo.parse_repo(theme.as_ref().map(Theme::path)).await?;
+        let template = template?;
 
-        let pages = Pages::from_git_file_info(pages_git_info, repo.tempdir.path()).await?;
-        let posts = Posts::from_git_file_info(posts_git_info, repo.tempdir.path()).await?;
-        let authors = Authors::generate(&pages, &posts);
+        let contents = Self::load_contents(
+            &repo,
+            &template,
+            pages_git_info,
+            posts_git_info,
+            images_git_info,
+        )
+        .await?;
 
-        Ok(Self {
+        let db = Self {
             repo,
+            theme,
             template,
-            posts,
-            pages,
-            authors,
-        })
+            posts: contents.posts,
+            pages: contents.pages,
+            authors: contents.authors,
+            menus: contents.menus,
+            params: contents.params,
+            index: contents.index,
+            problems: contents.problems,
+        };
+        db.update_routes().await;
+
+        Ok(db)
     }
 
-    pub async fn update(&mut self) -> Result<()> {
+    pub async fn update(&mut self) -> Result<UpdateReport> {
+        if let Some(theme) = &mut self.theme {
+            theme.fetch()?;
+        }
+
         let ParsedGitRepo {
             template,
             pages_git_info,
             posts_git_info,
-        } = self.repo.parse_repo().await?;
+            images_git_info,
+        } = self
+            .repo
+            .parse_repo(self.theme.as_ref().map(Theme::path))
+            .await?;
+
+        let (template, template_error) = match template {
+            Ok(template) => (Some(template), None),
+            Err(err) => (None, Some(err)),
+        };
+
+        let contents = Self::load_contents(
+            &self.repo,
+            template.as_ref().unwrap_or(&self.template),
+            pages_git_info,
+            posts_git_info,
+            images_git_info,
+        )
+        .await
+        .map_err(|err| match &template_error {
+            Some(template_error) => anyhow!(
+                "{}\n\nThe previous template is used because the new one is invalid:\n{}",
+                err,
+                template_error
+            ),
+            None => err,
+        })?;
+
+        if let Some(template) = template {
+            self.template = template;
+        }
+        self.posts = contents.posts;
+        self.pages = contents.pages;
+        self.authors = contents.authors;
+        self.menus = contents.menus;
+        self.params = contents.params;
+        self.index = contents.index;
+        self.problems = contents.problems;
+        self.update_routes().await;
+
+        Ok(UpdateReport {
+            template: template_error,
+            lint: self.lint(),
+        })
+    }
 
-        RouteTable::clear().await;
+    async fn load_contents(
+        repo: &Repo,
+        template: &Template,
+        pages_git_info: HashMap<PathBuf, GitFileInfo>,
+        posts_git_info: HashMap<PathBuf, GitFileInfo>,
+        images_git_info: HashMap<PathBuf, Oid>,
+    ) -> Result<Contents> {
+        let root = repo.tempdir.path();
+        let problems = Mutex::new(Vec::new());
+        let images = Images::from_git_blobs(images_git_info, root, &problems).await?;
 
-        self.template = template;
-        self.pages = Pages::from_git_file_info(pages_git_info, self.repo.tempdir.path()).await?;
-        self.posts = Posts::from_git_file_info(posts_git_info, self.repo.tempdir.path()).await?;
-        self.authors = Authors::generate(&self.pages, &self.posts);
+        let mut links = Pages::gen_urls(&pages_git_info);
+        links.extend(Posts::gen_urls(&posts_git_info));
 
-        Ok(())
+        let renderer = Renderer::new(root, template.shortcodes(), &images, &links, &problems);
+
+        let pages = Pages::from_git_file_info(pages_git_info, root, &renderer).await?;
+        let mut posts = Posts::from_git_file_info(posts_git_info, root, &renderer).await?;
+        template.check_layouts(&posts, &pages)?;
+
+        let mut problems = problems.into_inner().unwrap();
+        problems.sort();
+        problems.dedup();
+
+        let authors = Authors::generate(&pages, &posts);
+        let menus = Menus::from_file(&root.join("menu.toml"), &pages).await?;
+        let params = Params::from_site_file(&root.join("site.toml")).await?;
+        let index = Self::index_contents(&mut posts, &pages);
+
+        Ok(Contents {
+            posts,
+            pages,
+            authors,
+            menus,
+            params,
+            index,
+            problems,
+        })
+    }
+
+    async fn update_routes(&self) {
+        RouteTable::update(self.pages.routes(), self.posts.routes()).await;
+    }
+
+    pub fn get_content(&self, id: ContentId) -> Content<'_> {
+        match id {
+            ContentId::Post(id) => Content::Post(self.posts.get(id)),
+            ContentId::Page(id) => Content::Page(self.pages.get(id)),
+        }
+    }
+
+    pub fn search(&self, query: &SearchQuery, current_page_num_in_list: usize) -> SearchResult<'_> {
+        let mut scores: Option<HashMap<ContentId, f32>> = None;
+
+        for keyword in query.keywords() {
+            let result = self.index.search(keyword);
+
+            scores = Some(match scores {
+                Some(mut scores) => {
+                    scores.retain(|id, _| result.contains_key(id));
+                    scores
+                        .iter_mut()
+                        .for_each(|(id, score)| *score += result[id]);
+                    scores
+                }
+                None => result,
+            });
+        }
+
+        let is_matched = |content: &Content| {
+            query.filters.iter().all(|filter| match filter {
+                SearchFilter::Keyword(_) => true,
+                SearchFilter::TimeRange(time_range) => {
+                    content.create_time() >= time_range.from()
+                        && content.create_time() <= time_range.to()
+                }
+                SearchFilter::Author(authors) => match content.author() {
+                    Some(author) => authors.iter().any(|name| name == author),
+                    None => false,
+                },
+                SearchFilter::Type(content_types) => {
+                    content_types.contains(&content.content_type())
+                }
+                SearchFilter::Tag(tag) => content.tags().contains(tag),
+            })
+        };
+
+        let default_sort = if scores.is_some() {
+            SearchSort::Relevance
+        } else if Config::read().site.list_from_old_to_new {
+            SearchSort::Oldest
+        } else {
+            SearchSort::Newest
+        };
+
+        let mut matched = match scores {
+            Some(scores) => scores
+                .into_iter()
+                .map(|(id, score)| (self.get_content(id), score))
+                .filter(|(content, _)| is_matched(content))
+                .collect::<Vec<_>>(),
+            None => self
+                .posts
+                .data
+                .iter()
+                .map(Content::Post)
+                .chain(self.pages.data.iter().map(Content::Page))
+                .filter(is_matched)
+                .map(|content| (content, 0.0))
+                .collect::<Vec<_>>(),
+        };
+
+        let newest_first = |a: &Content, b: &Content| {
+            b.create_time()
+                .cmp(a.create_time())
+                .then_with(|| b.title().cmp(a.title()))
+        };
+
+        matched.sort_by(
+            |(a, a_score), (b, b_score)| match query.sort.unwrap_or(default_sort) {
+                SearchSort::Relevance => b_score
+                    .partial_cmp(a_score)
+                    .unwrap_or(Ordering::Equal)
+                    .then_with(|| newest_first(a, b)),
+                SearchSort::Newest => newest_first(a, b),
+                SearchSort::Oldest => newest_first(b, a),
+                SearchSort::Modified => b
+                    .modify_time()
+                    .cmp(a.modify_time())
+                    .then_with(|| newest_first(a, b)),
+                SearchSort::Title => a
+                    .title()
+                    .chars()
+                    .flat_map(char::to_lowercase)
+                    .cmp(b.title().chars().flat_map(char::to_lowercase))
+                    .then_with(|| a.title().cmp(b.title())),
+            },
+        );
+
+        let contents = matched
+            .into_iter()
+            .map(|(content, _)| content)
+            .collect::<Vec<_>>();
+        let facets = Facets::from_contents(&contents);
+
+        let (contents, total) =
+            paginate(contents.into_iter(), current_page_num_in_list).unwrap_or_default();
+
+        SearchResult {
+            contents,
+            total,
+            facets,
+        }
+    }
+
+    fn index_contents(posts: &mut Posts, pages: &Pages) -> SearchIndex {
+        let tokenizer = Tokenizer::new();
+
+        let post_tokens = posts
+            .data
+            .iter()
+            .map(|post| {
+                let content = Content::Post(post).text();
+                DocumentTokens::new(&tokenizer, &post.title, &content)
+            })
+            .collect::<Vec<_>>();
+
+        let related_posts_count = Config::read()
+            .site
+            .related_posts_count
+            .unwrap_or(Config::read().site.list_posts_count);
+
+        related::find_related(&posts.data, &post_tokens, related_posts_count)
+            .into_iter()
+            .zip(posts.data.iter_mut())
+            .for_each(|(related, post)| post.related = related);
+
+        let page_tokens = pages.data.iter().map(|page| {
+            let content = Content::Page(page).text();
+            DocumentTokens::new(&tokenizer, &page.title, &content)
+        });
+
+        let documents = post_tokens
+            .into_iter()
+            .enumerate()
+            .map(|(id, tokens)| (ContentId::Post(id), tokens))
+            .chain(
+                page_tokens
+                    .enumerate()
+                    .map(|(id, tokens)| (ContentId::Page(id), tokens)),
+            )
+            .collect::<Vec<_>>();
+
+        SearchIndex::new(tokenizer, &documents)
     }
 }
 
@@ -101,11 +399,16 @@ impl DatabaseManager {
 }
 
 pub enum DatabaseUpdateResult {
-    Success,
+    Success(UpdateReport),
     PermissionDenied,
     Error(Error),
 }
 
+pub struct UpdateReport {
+    pub template: Option<Error>,
+    pub lint: LintReport,
+}
+
 pub enum TimeRange {
     Year {
         year: i32,
@@ -170,6 +473,30 @@ impl TimeRange {
         Some(Self::Free { from, to })
     }
 
+    pub fn from_dates(from: Option<NaiveDate>, to: Option<NaiveDate>) -> Option<Self> {
+        let tz = &Config::read().application.timezone;
+
+        let from = match from {
+            Some(from) => tz.from_local_datetime(&from.and_hms_opt(0, 0, 0)?),
+            None => tz.timestamp_opt(0, 0),
+        }
+        .earliest()?;
+
+        let to = match to {
+            Some(to) => tz.from_local_datetime(&to.and_hms_opt(23, 59, 59)?),
+            None => tz.from_local_datetime(
+                &NaiveDate::from_ymd_opt(9999, 12, 31)?.and_hms_opt(23, 59, 59)?,
+            ),
+        }
+        .latest()?;
+
+        if from > to {
+            return None;
+        }
+
+        Some(Self::Free { from, to })
+    }
+
     pub fn from(&self) -> &DateTime<Tz> {
         match self {
             Self::Year { from, .. } => from,
@@ -197,6 +524,42 @@ impl Display for TimeRange {
     }
 }
 
+pub fn paginate<T>(
+    items: impl Iterator<Item = T>,
+    current_page_num_in_list: usize,
+) -> Option<(Vec<T>, usize)> {
+    let mut result = Vec::with_capacity(Config::read().site.list_posts_count);
+    let mut total = 0;
+
+    let mut items_iter =
+        items.skip((current_page_num_in_list - 1) * Config::read().site.list_posts_count);
+
+    for item in items_iter.by_ref() {
+        result.push(item);
+        total += 1;
+
+        if total == Config::read().site.list_posts_count {
+            break;
+        }
+    }
+
+    total +=
+        (current_page_num_in_list - 1) * Config::read().site.list_posts_count + items_iter.count();
+
+    if !result.is_empty() {
+        Some((result, total))
+    } else {
+        None
+    }
+}
+
+#[derive(Default)]
+pub struct SearchResult<'d> {
+    pub contents: Vec<Content<'d>>,
+    pub total: usize,
+    pub facets: Facets<'d>,
+}
+
 pub struct ListInfo {
     pub current_page_num_in_list: usize,
     pub total_page: usize,
@@ -231,3 +594,241 @@ impl ListInfo {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Git;
+    use git2::{IndexAddOption, Repository, RepositoryInitOptions, Signature, Time};
+    use std::fs;
+    use tempfile::TempDir;
+
+    const TEMPLATE_FILES: [&str; 7] = [
+        "header.html",
+        "footer.html",
+        "page_nav.html",
+        "page.html",
+        "post.html",
+        "summary.html",
+        "not_found.html",
+    ];
+
+    fn commit(repo: &Repository, author: &str, time: i64, files: &[(&str, &str)]) {
+        let root = repo.workdir().unwrap();
+        for (path, content) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"], IndexAddOption::DEFAULT, None).unwrap();
+        index.write().unwrap();
+
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::new(author, "author@example.com", &Time::new(time, 0)).unwrap();
+        let parent = repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+        let parents = parent.iter().collect::<Vec<_>>();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            "Update",
+            &tree,
+            &parents,
+        )
+        .unwrap();
+    }
+
+    fn init_remote() -> (TempDir, Repository) {
+        Config::init_for_test();
+        let _ = RouteTable::init();
+
+        let remote = TempDir::new().unwrap();
+        let repo = Repository::init_opts(
+            remote.path(),
+            RepositoryInitOptions::new().initial_head("main"),
+        )
+        .unwrap();
+
+        let files = TEMPLATE_FILES
+            .iter()
+            .map(|name| (format!("template/{}", name), ""))
+            .collect::<Vec<_>>();
+        commit(
+            &repo,
+            "Jane",
+            0,
+            &files
+                .iter()
+                .map(|(path, content)| (path.as_str(), *content))
+                .collect::<Vec<_>>(),
+        );
+
+        (remote, repo)
+    }
+
+    async fn open(remote: &TempDir) -> Database {
+        let git = Box::leak(Box::new(Git {
+            repository: remote.path().to_string_lossy().into_owned(),
+            branch: "main".to_owned(),
+            user: None,
+            password: None,
+            proxy: None,
+        }));
+
+        Database::open(Repo::open_remote(git).unwrap(), None)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn invalid_template_keeps_previous_one() {
+        let (remote, repo) = init_remote();
+        commit(
+            &repo,
+            "Jane",
+            0,
+            &[
+                ("template/shortcodes/hello.html", "<b>{:shortcode.0}</b>"),
+                ("posts/hello.md", "# Hello\n\n{{< hello old >}}\n"),
+            ],
+        );
+
+        let mut db = open(&remote).await;
+        assert!(db.posts.data[0].html.contains("<b>old</b>"));
+
+        commit(
+            &repo,
+            "Jane",
+            0,
+            &[
+                (
+                    "template/header.html",
+                    "<title>{:document.titel}</title>\n{:if site.name}",
+                ),
+                ("template/footer.html", "{:endfor}"),
+                ("template/shortcodes/hello.html", "<i>{:shortcode.0}</i>"),
+                ("posts/hello.md", "# Hello\n\n{{< hello new >}}\n"),
+            ],
+        );
+
+        let report = db.update().await.unwrap();
+        let error = report.template.unwrap().to_string();
+        assert!(
+            error.contains(
+                "header.html:1:8: Unknown parameter: {:document.titel}, did you mean `{:document.title}`?"
+            ),
+            "{}",
+            error
+        );
+        assert!(
+            error.contains("header.html:2:1: `{:if site.name}` is not closed by `{:endif}`"),
+            "{}",
+            error
+        );
+        assert!(
+            error.contains("footer.html:1:1: Unexpected `{:endfor}`"),
+            "{}",
+            error
+        );
+        assert!(db.posts.data[0].html.contains("<b>new</b>"));
+
+        commit(
+            &repo,
+            "Jane",
+            0,
+            &[("template/header.html", ""), ("template/footer.html", "")],
+        );
+
+        let report = db.update().await.unwrap();
+        assert!(report.template.is_none());
+        assert!(db.posts.data[0].html.contains("<i>new</i>"));
+    }
+
+    fn titles<'d>(result: &SearchResult<'d>) -> Vec<&'d str> {
+        result.contents.iter().map(Content::title).collect()
+    }
+
+    #[tokio::test]
+    async fn search_sorts_and_facets() {
+        let (remote, repo) = init_remote();
+        commit(
+            &repo,
+            "Jane",
+            1577836800,
+            &[(
+                "posts/banana.md",
+                "+++\ntags = [\"fruit\"]\n+++\nA yellow fruit\n",
+            )],
+        );
+        commit(
+            &repo,
+            "Joe",
+            1580515200,
+            &[(
+                "posts/apple.md",
+                "+++\ntags = [\"fruit\", \"red\"]\n+++\nAn apple is a red fruit, like every apple\n",
+            )],
+        );
+        commit(
+            &repo,
+            "Jane",
+            1614556800,
+            &[("pages/Cherry.md", "A red fruit\n")],
+        );
+        commit(
+            &repo,
+            "Joe",
+            1617235200,
+            &[(
+                "posts/banana.md",
+                "+++\ntags = [\"fruit\"]\n+++\nA long yellow fruit\n",
+            )],
+        );
+
+        let db = open(&remote).await;
+        let search = |query| db.search(&SearchQuery::from_uri_query(Some(query)), 1);
+
+        assert_eq!(titles(&search("")), ["Cherry", "apple", "banana"]);
+        assert_eq!(
+            titles(&search("sort=newest")),
+            ["Cherry", "apple", "banana"]
+        );
+        assert_eq!(
+            titles(&search("sort=oldest")),
+            ["banana", "apple", "Cherry"]
+        );
+        assert_eq!(
+            titles(&search("sort=modified")),
+            ["banana", "Cherry", "apple"]
+        );
+        assert_eq!(titles(&search("sort=title")), ["apple", "banana", "Cherry"]);
+        assert_eq!(titles(&search("keyword=red+OR+apple")), ["apple", "Cherry"]);
+        assert_eq!(
+            titles(&search("keyword=fruit&sort=title")),
+            ["apple", "banana", "Cherry"]
+        );
+        assert_eq!(
+            titles(&search("keyword=fruit&author=Jane&type=post")),
+            ["banana"]
+        );
+        assert_eq!(titles(&search("tag=red")), ["apple"]);
+        assert_eq!(titles(&search("from=2021-01-01")), ["Cherry"]);
+        assert!(search("keyword=grape").contents.is_empty());
+
+        let result = search("");
+        assert_eq!(result.total, 3);
+        assert_eq!(result.facets.authors, [("Jane", 2), ("Joe", 1)]);
+        assert_eq!(result.facets.years, [(2021, 1), (2020, 2)]);
+        assert_eq!(
+            result.facets.months,
+            [((2021, 3), 1), ((2020, 2), 1), ((2020, 1), 1)]
+        );
+        assert_eq!(result.facets.tags, [("fruit", 2), ("red", 1)]);
+
+        let result = search("keyword=red");
+        assert_eq!(result.facets.authors, [("Jane", 1), ("Joe", 1)]);
+        assert_eq!(result.facets.tags, [("fruit", 1), ("red", 1)]);
+    }
+}
@@ -1,13 +1,20 @@
-use super::git::GitFileInfo;
-use crate::{Config, RouteTable};
-use anyhow::Result;
+use super::{
+    front_matter::FrontMatter,
+    git::GitFileInfo,
+    template::{
+        format::{self, Format},
+        markdown::{self, Renderer},
+    },
+    text, Params,
+};
+use crate::Config;
+use anyhow::{anyhow, Result};
 use chrono::{DateTime, TimeZone};
 use chrono_tz::Tz;
 use regex::{Captures, Regex};
 use std::{
     cmp::Ordering,
     collections::{BinaryHeap, HashMap},
-    ffi::OsStr,
     path::{Path, PathBuf},
 };
 use tokio::fs;
@@ -21,42 +28,60 @@ impl Pages {
     pub async fn from_git_file_info(
         file_info: HashMap<PathBuf, GitFileInfo>,
         tempdir: &Path,
+        renderer: &Renderer<'_>,
     ) -> Result<Self> {
         let mut data = BinaryHeap::new();
         let page_url_regex_args = Regex::new(r":slug").unwrap();
 
         for (path, info) in file_info.into_iter() {
-            if path.extension() == Some(OsStr::new("md")) {
+            if let Some(format) = format::from_path(&path) {
                 let abs_path = tempdir.join(&path);
 
-                let title = path.file_stem().unwrap().to_str().unwrap().to_owned();
                 let content = fs::read_to_string(abs_path).await?;
+                let (front_matter, content) = FrontMatter::extract(content, &path)?;
 
                 let page = Page::new(
-                    title,
+                    &path,
                     content,
+                    format,
+                    front_matter,
                     info.author,
                     info.create_time.unwrap(),
                     info.modify_time,
                     &page_url_regex_args,
-                );
+                    &renderer.with_path(&path),
+                )
+                .map_err(|err| anyhow!("Failed to render {}: {}", path.display(), err))?;
                 data.push(page);
             }
         }
 
         let data = data.into_sorted_vec();
 
+        Ok(Self { data })
+    }
+
+    pub fn routes(&self) -> HashMap<String, usize> {
         let site_url_len = Config::read().site.url.len();
 
-        let map = data
+        self.data
             .iter()
             .enumerate()
             .map(|(idx, page)| (page.url[site_url_len..].to_owned(), idx))
-            .collect::<HashMap<String, usize>>();
+            .collect()
+    }
 
-        RouteTable::update_pages(map).await;
+    pub fn gen_urls(file_info: &HashMap<PathBuf, GitFileInfo>) -> HashMap<PathBuf, String> {
+        let page_url_regex_args = Regex::new(r":slug").unwrap();
 
-        Ok(Self { data })
+        file_info
+            .iter()
+            .filter(|(path, _)| format::from_path(path).is_some())
+            .map(|(path, _)| {
+                let title = path.file_stem().unwrap().to_str().unwrap();
+                (path.to_owned(), Page::gen_url(title, &page_url_regex_args))
+            })
+            .collect()
     }
 
     pub fn get(&self, id: usize) -> &Page {
@@ -66,45 +91,83 @@ impl Pages {
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Page {
+    pub path: PathBuf,
     pub title: String,
     pub url: String,
     pub content: String,
+    pub html: String,
+    pub summary_html: String,
+    pub has_more: bool,
+    pub word_count: usize,
+    pub reading_time: usize,
+    pub toc: String,
     pub author: Option<String>,
+    pub layout: Option<String>,
+    pub params: Params,
+    pub directory: Option<String>,
     pub create_time: DateTime<Tz>,
     pub modify_time: DateTime<Tz>,
 }
 
 impl Page {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        title: String,
+        path: &Path,
         content: String,
+        format: &dyn Format,
+        front_matter: FrontMatter,
         author: Option<String>,
         create_time: i64,
         modify_time: i64,
         url_regex_args: &Regex,
-    ) -> Self {
+        renderer: &Renderer<'_>,
+    ) -> Result<Self> {
+        let title = path.file_stem().unwrap().to_str().unwrap().to_owned();
         let tz = &Config::read().application.timezone;
         let create_time = tz.timestamp(create_time, 0);
         let modify_time = tz.timestamp(modify_time, 0);
 
+        let url = Self::gen_url(&title, url_regex_args);
+        let (html, toc) = format.render(renderer, &content)?;
+        let (summary_html, has_more) =
+            renderer.summary(format, &content, front_matter.summary.as_deref(), &html)?;
+        let word_count = text::word_count(&markdown::html_to_text(&html));
+        let reading_time = word_count
+            .div_ceil(Config::read().site.words_per_minute)
+            .max(1);
+
+        Ok(Self {
+            path: path.to_owned(),
+            title,
+            url,
+            content,
+            html,
+            summary_html,
+            has_more,
+            word_count,
+            reading_time,
+            toc,
+            author,
+            layout: front_matter.layout,
+            params: front_matter.params,
+            directory: front_matter.directory,
+            create_time,
+            modify_time,
+        })
+    }
+}
+
+impl Page {
+    fn gen_url(title: &str, url_regex_args: &Regex) -> String {
         let path =
             url_regex_args.replace_all(&Config::read().url_patterns.page, |cap: &Captures| {
                 match &cap[0] {
-                    ":slug" => &title,
+                    ":slug" => title,
                     _ => unreachable!(),
                 }
             });
 
-        let url = format!("{}{}", &Config::read().site.url, path);
-
-        Self {
-            title,
-            url,
-            content,
-            author,
-            create_time,
-            modify_time,
-        }
+        format!("{}{}", &Config::read().site.url, path)
     }
 }
 
@@ -0,0 +1,62 @@
+use crate::Config;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::{collections::HashMap, io::ErrorKind, path::Path};
+use tokio::fs;
+use toml::{value::Table, Value};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(from = "Table")]
+pub struct Params(HashMap<String, String>);
+
+impl Params {
+    pub async fn from_site_file(path: &Path) -> Result<Self> {
+        let mut params: Self = match fs::read_to_string(path).await {
+            Ok(file) => toml::from_str(&file)
+                .map_err(|err| anyhow!("Failed to parse site.toml: {}", err))?,
+            Err(err) if err.kind() == ErrorKind::NotFound => Self::default(),
+            Err(err) => return Err(err.into()),
+        };
+
+        params.0.extend(Config::read().site.params.0.clone());
+
+        Ok(params)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(|value| value.as_str())
+    }
+
+    fn flatten(params: &mut HashMap<String, String>, prefix: &str, table: Table) {
+        for (key, value) in table {
+            let key = format!("{}{}", prefix, key);
+
+            match value {
+                Value::Table(table) => Self::flatten(params, &format!("{}.", key), table),
+                value => {
+                    params.insert(key, Self::value_to_string(value));
+                }
+            }
+        }
+    }
+
+    fn value_to_string(value: Value) -> String {
+        match value {
+            Value::String(str) => str,
+            Value::Array(values) => values
+                .into_iter()
+                .map(Self::value_to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+            value => value.to_string(),
+        }
+    }
+}
+
+impl From<Table> for Params {
+    fn from(table: Table) -> Self {
+        let mut params = HashMap::new();
+        Self::flatten(&mut params, "", table);
+        Self(params)
+    }
+}
@@ -1,14 +1,20 @@
-use super::{git::GitFileInfo, TimeRange};
-use crate::{Config, RouteTable};
-use anyhow::Result;
+use super::{
+    front_matter::FrontMatter,
+    git::GitFileInfo,
+    template::{
+        format::{self, Format},
+        markdown::{self, Renderer},
+    },
+    text, Params, TimeRange,
+};
+use crate::Config;
+use anyhow::{anyhow, Result};
 use chrono::{DateTime, Datelike, TimeZone};
 use chrono_tz::Tz;
 use regex::{Captures, Regex};
 use std::{
-    borrow::Cow,
     cmp::Ordering,
     collections::{BinaryHeap, HashMap},
-    ffi::OsStr,
     path::{Path, PathBuf},
 };
 use tokio::fs;
@@ -16,54 +22,103 @@ use tokio::fs;
 #[derive(Debug)]
 pub struct Posts {
     pub data: Vec<Post>,
+    pub series: HashMap<String, Vec<usize>>,
 }
 
 impl Posts {
     pub async fn from_git_file_info(
         file_info: HashMap<PathBuf, GitFileInfo>,
         tempdir: &Path,
+        renderer: &Renderer<'_>,
     ) -> Result<Self> {
         let mut data = BinaryHeap::new();
         let post_url_regex_args = Regex::new(r":slug|:year|:month").unwrap();
 
         for (path, info) in file_info.into_iter() {
-            if path.extension() == Some(OsStr::new("md")) {
+            if let Some(format) = format::from_path(&path) {
                 let abs_path = tempdir.join(&path);
 
-                let title = path.file_stem().unwrap().to_str().unwrap().to_owned();
                 let content = fs::read_to_string(abs_path).await?;
+                let (front_matter, content) = FrontMatter::extract(content, &path)?;
 
                 let post = Post::new(
-                    title,
+                    &path,
                     content,
+                    format,
+                    front_matter,
                     info.author,
                     info.create_time.unwrap(),
                     info.modify_time,
                     &post_url_regex_args,
-                );
+                    &renderer.with_path(&path),
+                )
+                .map_err(|err| anyhow!("Failed to render {}: {}", path.display(), err))?;
                 data.push(post);
             }
         }
 
         let data = data.into_sorted_vec();
 
+        let mut series = HashMap::new();
+
+        data.iter()
+            .enumerate()
+            .filter_map(|(idx, post)| post.series.as_ref().map(|series| (idx, series)))
+            .for_each(|(idx, name)| {
+                series
+                    .entry(name.to_owned())
+                    .or_insert_with(Vec::new)
+                    .push(idx);
+            });
+
+        Ok(Self { data, series })
+    }
+
+    pub fn routes(&self) -> HashMap<String, usize> {
         let site_url_len = Config::read().site.url.len();
 
-        let map = data
+        self.data
             .iter()
             .enumerate()
             .map(|(idx, post)| (post.url[site_url_len..].to_owned(), idx))
-            .collect::<HashMap<String, usize>>();
+            .collect()
+    }
 
-        RouteTable::update_posts(map).await;
+    pub fn gen_urls(file_info: &HashMap<PathBuf, GitFileInfo>) -> HashMap<PathBuf, String> {
+        let post_url_regex_args = Regex::new(r":slug|:year|:month").unwrap();
+        let tz = &Config::read().application.timezone;
 
-        Ok(Self { data })
+        file_info
+            .iter()
+            .filter(|(path, _)| format::from_path(path).is_some())
+            .map(|(path, info)| {
+                let title = path.file_stem().unwrap().to_str().unwrap();
+                let create_time = tz.timestamp_opt(info.create_time.unwrap(), 0).unwrap();
+                let url = Post::gen_url(title, &create_time, &post_url_regex_args);
+                (path.to_owned(), url)
+            })
+            .collect()
     }
 
     pub fn get(&self, id: usize) -> &Post {
         &self.data[id]
     }
 
+    pub fn get_neighbours(&self, id: usize) -> (Option<&Post>, Option<&Post>) {
+        let older = id.checked_sub(1).map(|id| &self.data[id]);
+        let newer = self.data.get(id + 1);
+
+        if Config::read().site.list_from_old_to_new {
+            (older, newer)
+        } else {
+            (newer, older)
+        }
+    }
+
+    pub fn get_series(&self, name: &str) -> Option<&[usize]> {
+        self.series.get(name).map(|ids| ids.as_slice())
+    }
+
     pub fn get_multi(
         &self,
         id: &[usize],
@@ -95,112 +150,94 @@ impl Posts {
         Self::page_filter(self.data.iter(), current_page_num_in_list)
     }
 
-    pub fn search(
-        &self,
-        filters: &[PostFilter],
-        current_page_num_in_list: usize,
-    ) -> Option<(Vec<&Post>, usize)> {
-        let mut posts: Box<dyn DoubleEndedIterator<Item = &Post>> = Box::new(self.data.iter());
-
-        for filter in filters {
-            posts = match filter {
-                PostFilter::Keyword(keyword) => {
-                    let filter = move |post: &&Post| {
-                        post.title.contains(keyword) || post.content.contains(keyword)
-                    };
-                    Box::new(posts.filter(filter))
-                }
-                PostFilter::TimeRange(time_range) => {
-                    let filter = move |post: &&Post| {
-                        &post.create_time >= time_range.from()
-                            && &post.create_time <= time_range.to()
-                    };
-                    Box::new(posts.filter(filter))
-                }
-                PostFilter::Author(author) => {
-                    let filter = move |post: &&Post| match &post.author {
-                        Some(post_author) => post_author == author,
-                        None => false,
-                    };
-                    Box::new(posts.filter(filter))
-                }
-            }
-        }
-
-        Self::page_filter(posts, current_page_num_in_list)
-    }
-
     fn page_filter<'p>(
         posts: impl DoubleEndedIterator<Item = &'p Post>,
         current_page_num_in_list: usize,
     ) -> Option<(Vec<&'p Post>, usize)> {
-        let mut result = Vec::with_capacity(Config::read().site.list_posts_count);
-        let mut total = 0;
-
         if Config::read().site.list_from_old_to_new {
-            let mut posts_iter =
-                posts.skip((current_page_num_in_list - 1) * Config::read().site.list_posts_count);
-
-            for post in posts_iter.by_ref() {
-                result.push(post);
-                total += 1;
-
-                if total == Config::read().site.list_posts_count {
-                    break;
-                }
-            }
-
-            total += (current_page_num_in_list - 1) * Config::read().site.list_posts_count
-                + posts_iter.count();
+            super::paginate(posts, current_page_num_in_list)
         } else {
-            let mut posts_iter = posts
-                .rev()
-                .skip((current_page_num_in_list - 1) * Config::read().site.list_posts_count);
-
-            for post in posts_iter.by_ref() {
-                result.push(post);
-                total += 1;
-
-                if total == Config::read().site.list_posts_count {
-                    break;
-                }
-            }
-
-            total += (current_page_num_in_list - 1) * Config::read().site.list_posts_count
-                + posts_iter.count();
-        }
-
-        if !result.is_empty() {
-            Some((result, total))
-        } else {
-            None
+            super::paginate(posts.rev(), current_page_num_in_list)
         }
     }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Post {
+    pub path: PathBuf,
     pub title: String,
     pub url: String,
     pub content: String,
+    pub html: String,
+    pub summary_html: String,
+    pub has_more: bool,
+    pub word_count: usize,
+    pub reading_time: usize,
+    pub toc: String,
     pub author: Option<String>,
+    pub series: Option<String>,
+    pub tags: Vec<String>,
+    pub related: Vec<usize>,
+    pub layout: Option<String>,
+    pub params: Params,
+    pub directory: Option<String>,
     pub create_time: DateTime<Tz>,
     pub modify_time: DateTime<Tz>,
 }
 
 impl Post {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        title: String,
+        path: &Path,
         content: String,
+        format: &dyn Format,
+        front_matter: FrontMatter,
         author: Option<String>,
         create_time: i64,
         modify_time: i64,
         url_regex_args: &Regex,
-    ) -> Self {
+        renderer: &Renderer<'_>,
+    ) -> Result<Self> {
+        let title = path.file_stem().unwrap().to_str().unwrap().to_owned();
         let tz = &Config::read().application.timezone;
         let create_time = tz.timestamp(create_time, 0);
         let modify_time = tz.timestamp(modify_time, 0);
 
+        let url = Self::gen_url(&title, &create_time, url_regex_args);
+        let (html, toc) = format.render(renderer, &content)?;
+        let (summary_html, has_more) =
+            renderer.summary(format, &content, front_matter.summary.as_deref(), &html)?;
+        let word_count = text::word_count(&markdown::html_to_text(&html));
+        let reading_time = word_count
+            .div_ceil(Config::read().site.words_per_minute)
+            .max(1);
+
+        Ok(Self {
+            path: path.to_owned(),
+            title,
+            url,
+            content,
+            html,
+            summary_html,
+            has_more,
+            word_count,
+            reading_time,
+            toc,
+            author,
+            series: front_matter.series,
+            tags: front_matter.tags,
+            related: Vec::new(),
+            layout: front_matter.layout,
+            params: front_matter.params,
+            directory: front_matter.directory,
+            create_time,
+            modify_time,
+        })
+    }
+}
+
+impl Post {
+    fn gen_url(title: &str, create_time: &DateTime<Tz>, url_regex_args: &Regex) -> String {
         let year = create_time.year().to_string();
 
         let month = create_time.month().to_string();
@@ -208,23 +245,14 @@ impl Post {
         let path =
             url_regex_args.replace_all(&Config::read().url_patterns.post, |cap: &Captures| {
                 match &cap[0] {
-                    ":slug" => &title,
+                    ":slug" => title,
                     ":year" => &year,
                     ":month" => &month,
                     _ => unreachable!(),
                 }
             });
 
-        let url = format!("{}{}", &Config::read().site.url, path);
-
-        Self {
-            title,
-            url,
-            content,
-            author,
-            create_time,
-            modify_time,
-        }
+        format!("{}{}", &Config::read().site.url, path)
     }
 }
 
@@ -242,45 +270,3 @@ impl PartialOrd for Post {
         Some(self.cmp(other))
     }
 }
-
-pub enum PostFilter<'f> {
-    Keyword(&'f str),
-    TimeRange(TimeRange),
-    Author(&'f str),
-}
-
-impl<'f> PostFilter<'f> {
-    pub fn from_uri_query(query: &'f str) -> Option<Vec<Self>> {
-        let filters = query
-            .split('&')
-            .filter_map(|part| {
-                let (key, value) = part.split_once('=')?;
-
-                match key {
-                    "keyword" => Some(Self::Keyword(value)),
-                    "time_range" => {
-                        let (from, to) = part.split_once('-')?;
-                        let (from, to) = (from.parse().ok()?, to.parse().ok()?);
-                        Some(Self::TimeRange(TimeRange::from_timestamps(from, to)?))
-                    }
-                    "author" => Some(Self::Author(value)),
-                    _ => None,
-                }
-            })
-            .collect::<Vec<_>>();
-
-        if !filters.is_empty() {
-            Some(filters)
-        } else {
-            None
-        }
-    }
-
-    pub fn to_breadcrumb(&self) -> (&str, Cow<str>) {
-        match self {
-            Self::Keyword(keyword) => ("Keyword", Cow::Borrowed(keyword)),
-            Self::TimeRange(time_range) => ("Time Range", Cow::Owned(time_range.to_string())),
-            Self::Author(author) => ("Author", Cow::Borrowed(author)),
-        }
-    }
-}
@@ -0,0 +1,127 @@
+use super::{text::DocumentTokens, Post};
+use std::{cmp::Ordering, collections::HashMap};
+
+const SIGNATURE_TERMS_COUNT: usize = 32;
+const TAG_WEIGHT: f32 = 0.5;
+const AUTHOR_WEIGHT: f32 = 0.1;
+
+pub fn find_related(posts: &[Post], tokens: &[DocumentTokens], count: usize) -> Vec<Vec<usize>> {
+    if count == 0 || posts.len() < 2 {
+        return vec![Vec::new(); posts.len()];
+    }
+
+    let mut term_ids = HashMap::new();
+
+    let term_freqs = tokens
+        .iter()
+        .map(|tokens| {
+            let mut term_freq = HashMap::new();
+
+            for term in tokens.iter() {
+                let next_id = term_ids.len();
+                let id = *term_ids.entry(term.as_str()).or_insert(next_id);
+                *term_freq.entry(id).or_insert(0.0) += 1.0;
+            }
+
+            term_freq
+        })
+        .collect::<Vec<HashMap<usize, f32>>>();
+
+    let mut doc_freqs = vec![0usize; term_ids.len()];
+    term_freqs
+        .iter()
+        .for_each(|term_freq| term_freq.keys().for_each(|id| doc_freqs[*id] += 1));
+
+    let total = posts.len() as f32;
+
+    // Only the highest weighted terms shared with other posts are kept for each post,
+    // so the cost of scoring stays bounded by the signature size instead of the post length
+    let signatures = term_freqs
+        .into_iter()
+        .map(|term_freq| {
+            let mut weights = term_freq
+                .into_iter()
+                .filter(|(id, _)| doc_freqs[*id] > 1)
+                .map(|(id, freq)| {
+                    let idf = (total / doc_freqs[id] as f32).ln();
+                    (id, (1.0 + freq.ln()) * idf)
+                })
+                .filter(|(_, weight)| *weight > 0.0)
+                .collect::<Vec<_>>();
+
+            weights.sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+            weights.truncate(SIGNATURE_TERMS_COUNT);
+
+            let norm = weights.iter().map(|(_, w)| w * w).sum::<f32>().sqrt();
+            weights.iter_mut().for_each(|(_, w)| *w /= norm);
+
+            weights
+        })
+        .collect::<Vec<_>>();
+
+    let mut postings = vec![Vec::new(); term_ids.len()];
+    signatures.iter().enumerate().for_each(|(idx, signature)| {
+        signature
+            .iter()
+            .for_each(|(id, weight)| postings[*id].push((idx, *weight)));
+    });
+
+    let mut tags = HashMap::new();
+    posts.iter().enumerate().for_each(|(idx, post)| {
+        post.tags.iter().for_each(|tag| {
+            tags.entry(tag.as_str()).or_insert_with(Vec::new).push(idx);
+        })
+    });
+
+    let mut scores = vec![0.0f32; posts.len()];
+    let mut touched = Vec::new();
+
+    posts
+        .iter()
+        .enumerate()
+        .map(|(idx, post)| {
+            let mut add_score = |other: usize, score: f32| {
+                if other != idx {
+                    if scores[other] == 0.0 {
+                        touched.push(other);
+                    }
+                    scores[other] += score;
+                }
+            };
+
+            for (id, weight) in &signatures[idx] {
+                for (other, other_weight) in &postings[*id] {
+                    add_score(*other, weight * other_weight);
+                }
+            }
+
+            for tag in &post.tags {
+                for other in &tags[tag.as_str()] {
+                    add_score(*other, TAG_WEIGHT / post.tags.len() as f32);
+                }
+            }
+
+            if post.author.is_some() {
+                for other in &touched {
+                    if posts[*other].author == post.author {
+                        scores[*other] += AUTHOR_WEIGHT;
+                    }
+                }
+            }
+
+            touched.sort_unstable_by(|a, b| {
+                scores[*b]
+                    .partial_cmp(&scores[*a])
+                    .unwrap_or(Ordering::Equal)
+                    .then(b.cmp(a))
+            });
+
+            let related = touched.iter().take(count).copied().collect();
+
+            touched.iter().for_each(|other| scores[*other] = 0.0);
+            touched.clear();
+
+            related
+        })
+        .collect()
+}
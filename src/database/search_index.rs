@@ -0,0 +1,451 @@
+use super::{
+    text::{self, DocumentTokens, Tokenizer},
+    ContentId,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Debug, Formatter},
+};
+
+const BM25_K1: f32 = 1.2;
+const BM25_B: f32 = 0.75;
+const TITLE_BOOST: f32 = 3.0;
+
+pub struct SearchIndex {
+    tokenizer: Tokenizer,
+    terms: HashMap<String, Vec<Posting>>,
+    ids: Vec<ContentId>,
+    docs: Vec<DocumentLength>,
+    avg_title_len: f32,
+    avg_body_len: f32,
+}
+
+struct Posting {
+    doc: usize,
+    title_positions: Vec<u32>,
+    body_positions: Vec<u32>,
+}
+
+struct DocumentLength {
+    title: usize,
+    body: usize,
+}
+
+impl SearchIndex {
+    pub fn new(tokenizer: Tokenizer, documents: &[(ContentId, DocumentTokens)]) -> Self {
+        let mut terms: HashMap<String, Vec<Posting>> = HashMap::new();
+
+        for (doc, (_, tokens)) in documents.iter().enumerate() {
+            let mut postings: HashMap<&str, Posting> = HashMap::new();
+
+            for (pos, token) in tokens.title.iter().enumerate() {
+                postings
+                    .entry(token)
+                    .or_insert_with(|| Posting::new(doc))
+                    .title_positions
+                    .push(pos as u32);
+            }
+
+            for (pos, token) in tokens.body.iter().enumerate() {
+                postings
+                    .entry(token)
+                    .or_insert_with(|| Posting::new(doc))
+                    .body_positions
+                    .push(pos as u32);
+            }
+
+            for (token, posting) in postings {
+                terms.entry(token.to_owned()).or_default().push(posting);
+            }
+        }
+
+        let ids = documents.iter().map(|(id, _)| *id).collect();
+
+        let docs = documents
+            .iter()
+            .map(|(_, tokens)| DocumentLength {
+                title: tokens.title.len(),
+                body: tokens.body.len(),
+            })
+            .collect::<Vec<_>>();
+
+        let total = docs.len().max(1) as f32;
+        let avg_title_len = docs.iter().map(|doc| doc.title).sum::<usize>() as f32 / total;
+        let avg_body_len = docs.iter().map(|doc| doc.body).sum::<usize>() as f32 / total;
+
+        Self {
+            tokenizer,
+            terms,
+            ids,
+            docs,
+            avg_title_len,
+            avg_body_len,
+        }
+    }
+
+    pub fn search(&self, keyword: &str) -> HashMap<ContentId, f32> {
+        let query = KeywordQuery::parse(keyword, &self.tokenizer);
+
+        let mut result: Option<HashSet<usize>> = None;
+
+        for group in &query.groups {
+            let matched = group
+                .iter()
+                .flat_map(|phrase| self.match_phrase(phrase))
+                .collect::<HashSet<_>>();
+
+            result = Some(match result {
+                Some(result) => result.intersection(&matched).copied().collect(),
+                None => matched,
+            });
+        }
+
+        let mut result = result.unwrap_or_else(|| (0..self.docs.len()).collect());
+
+        for phrase in &query.exclusions {
+            self.match_phrase(phrase).into_iter().for_each(|doc| {
+                result.remove(&doc);
+            });
+        }
+
+        let mut scores = result
+            .into_iter()
+            .map(|doc| (doc, 0.0))
+            .collect::<HashMap<_, _>>();
+
+        let terms = query
+            .groups
+            .iter()
+            .flatten()
+            .flatten()
+            .collect::<HashSet<_>>();
+
+        for term in terms {
+            let postings = self.postings(term);
+            let idf = self.idf(postings.len());
+
+            for (doc, posting) in &postings {
+                if let Some(score) = scores.get_mut(doc) {
+                    *score += idf * self.bm25_tf(posting);
+                }
+            }
+        }
+
+        scores
+            .into_iter()
+            .map(|(doc, score)| (self.ids[doc], score))
+            .collect()
+    }
+
+    pub fn query_terms(&self, keyword: &str) -> HashSet<String> {
+        let query = KeywordQuery::parse(keyword, &self.tokenizer);
+
+        query
+            .groups
+            .iter()
+            .flatten()
+            .flatten()
+            .flat_map(|term| self.expand_term(term))
+            .map(str::to_owned)
+            .collect()
+    }
+
+    pub fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    // A CJK character next to other CJK characters is only indexed in bigrams, so it matches them too
+    fn expand_term<'a>(&'a self, term: &'a str) -> Vec<&'a str> {
+        let mut chars = term.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if text::is_cjk(c) => self
+                .terms
+                .keys()
+                .filter(|term| term.contains(c))
+                .map(String::as_str)
+                .collect(),
+            _ => vec![term],
+        }
+    }
+
+    fn postings(&self, term: &str) -> HashMap<usize, Posting> {
+        let mut postings: HashMap<usize, Posting> = HashMap::new();
+
+        for term in self.expand_term(term) {
+            for posting in self.terms.get(term).into_iter().flatten() {
+                let merged = postings
+                    .entry(posting.doc)
+                    .or_insert_with(|| Posting::new(posting.doc));
+                merged.title_positions.extend(&posting.title_positions);
+                merged.body_positions.extend(&posting.body_positions);
+            }
+        }
+
+        for posting in postings.values_mut() {
+            posting.title_positions.sort_unstable();
+            posting.body_positions.sort_unstable();
+        }
+
+        postings
+    }
+
+    fn match_phrase(&self, phrase: &[String]) -> Vec<usize> {
+        let postings = phrase
+            .iter()
+            .map(|term| self.postings(term))
+            .collect::<Vec<_>>();
+
+        if postings.is_empty() || postings.iter().any(HashMap::is_empty) {
+            return Vec::new();
+        }
+
+        postings[0]
+            .iter()
+            .filter(|(doc, first)| {
+                let rest = match postings[1..]
+                    .iter()
+                    .map(|postings| postings.get(doc))
+                    .collect::<Option<Vec<_>>>()
+                {
+                    Some(rest) => rest,
+                    None => return false,
+                };
+
+                Self::is_consecutive(&first.title_positions, &rest, |p| &p.title_positions)
+                    || Self::is_consecutive(&first.body_positions, &rest, |p| &p.body_positions)
+            })
+            .map(|(doc, _)| *doc)
+            .collect()
+    }
+
+    fn is_consecutive<F>(first: &[u32], rest: &[&Posting], positions: F) -> bool
+    where
+        F: Fn(&Posting) -> &Vec<u32>,
+    {
+        first.iter().any(|start| {
+            rest.iter().enumerate().all(|(offset, posting)| {
+                positions(posting)
+                    .binary_search(&(start + offset as u32 + 1))
+                    .is_ok()
+            })
+        })
+    }
+
+    fn idf(&self, doc_freq: usize) -> f32 {
+        let total = self.docs.len() as f32;
+        let doc_freq = doc_freq as f32;
+
+        ((total - doc_freq + 0.5) / (doc_freq + 0.5) + 1.0).ln()
+    }
+
+    fn bm25_tf(&self, posting: &Posting) -> f32 {
+        let doc = &self.docs[posting.doc];
+
+        let title = Self::saturate(posting.title_positions.len(), doc.title, self.avg_title_len);
+        let body = Self::saturate(posting.body_positions.len(), doc.body, self.avg_body_len);
+
+        TITLE_BOOST * title + body
+    }
+
+    fn saturate(freq: usize, len: usize, avg_len: f32) -> f32 {
+        if freq == 0 {
+            return 0.0;
+        }
+
+        let freq = freq as f32;
+        let norm = 1.0 - BM25_B + BM25_B * len as f32 / avg_len.max(1.0);
+
+        freq * (BM25_K1 + 1.0) / (freq + BM25_K1 * norm)
+    }
+}
+
+impl Debug for SearchIndex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SearchIndex")
+            .field("terms", &self.terms.len())
+            .field("docs", &self.docs.len())
+            .finish()
+    }
+}
+
+impl Posting {
+    fn new(doc: usize) -> Self {
+        Self {
+            doc,
+            title_positions: Vec::new(),
+            body_positions: Vec::new(),
+        }
+    }
+}
+
+// `groups` are ANDed together, phrases within a group are ORed
+struct KeywordQuery {
+    groups: Vec<Vec<Vec<String>>>,
+    exclusions: Vec<Vec<String>>,
+}
+
+impl KeywordQuery {
+    fn parse(keyword: &str, tokenizer: &Tokenizer) -> Self {
+        let mut groups: Vec<Vec<Vec<String>>> = Vec::new();
+        let mut exclusions = Vec::new();
+        let mut is_or = false;
+
+        let mut rest = keyword.trim_start();
+
+        while !rest.is_empty() {
+            let is_exclusion = rest.starts_with('-');
+            if is_exclusion {
+                rest = &rest[1..];
+            }
+
+            let (part, is_quoted) = if let Some(quoted) = rest.strip_prefix('"') {
+                let end = quoted.find('"').unwrap_or(quoted.len());
+                rest = quoted.get(end + 1..).unwrap_or("");
+                (&quoted[..end], true)
+            } else {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                let part = &rest[..end];
+                rest = &rest[end..];
+                (part, false)
+            };
+
+            rest = rest.trim_start();
+
+            if part == "OR" && !is_quoted && !is_exclusion {
+                is_or = true;
+                continue;
+            }
+
+            let phrase = tokenizer.tokenize(part);
+
+            if phrase.is_empty() {
+                continue;
+            }
+
+            if is_exclusion {
+                exclusions.push(phrase);
+            } else if let (true, Some(group)) = (is_or, groups.last_mut()) {
+                group.push(phrase);
+            } else {
+                groups.push(vec![phrase]);
+            }
+
+            is_or = false;
+        }
+
+        Self { groups, exclusions }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    fn index(documents: &[(&str, &str)]) -> SearchIndex {
+        Config::init_for_test();
+        let tokenizer = Tokenizer::new();
+        let documents = documents
+            .iter()
+            .enumerate()
+            .map(|(id, (title, body))| {
+                (
+                    ContentId::Post(id),
+                    DocumentTokens::new(&tokenizer, title, body),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        SearchIndex::new(tokenizer, &documents)
+    }
+
+    fn search(index: &SearchIndex, keyword: &str) -> Vec<usize> {
+        let mut result = index.search(keyword).into_iter().collect::<Vec<_>>();
+        result.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        result
+            .into_iter()
+            .map(|(id, _)| match id {
+                ContentId::Post(id) => id,
+                ContentId::Page(_) => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bm25_ranking() {
+        let index = index(&[
+            ("", "rust is fast"),
+            ("", "rust rust rust is fast"),
+            ("", "rust is fast and safe and fun to write every day"),
+            ("", "go is fast"),
+        ]);
+
+        assert_eq!(search(&index, "rust"), [1, 0, 2]);
+        assert_eq!(search(&index, "fast go"), [3]);
+        assert!(search(&index, "missing").is_empty());
+    }
+
+    #[test]
+    fn title_boost() {
+        let index = index(&[
+            ("Notes", "a long post about rust and many other things"),
+            ("Rust", "a long post about many other things"),
+            ("Notes", "rust rust"),
+        ]);
+
+        assert_eq!(search(&index, "rust")[0], 1);
+    }
+
+    #[test]
+    fn keyword_queries() {
+        let index = index(&[
+            ("", "rust web framework"),
+            ("", "go web server"),
+            ("", "framework for the web in rust"),
+            ("", "unsafe rust"),
+        ]);
+        let sorted = |keyword| {
+            let mut result = search(&index, keyword);
+            result.sort_unstable();
+            result
+        };
+
+        assert_eq!(sorted("rust web"), [0, 2]);
+        assert_eq!(sorted("rust OR go"), [0, 1, 2, 3]);
+        assert_eq!(sorted("server OR unsafe web"), [1]);
+        assert_eq!(sorted(r#""web framework""#), [0]);
+        assert_eq!(sorted(r#""web framework"#), [0]);
+        assert_eq!(sorted("rust -unsafe"), [0, 2]);
+        assert_eq!(sorted(r#"rust -"web framework""#), [2, 3]);
+        assert_eq!(sorted("-web"), [3]);
+        assert_eq!(sorted("OR"), [0, 1, 2, 3]);
+        assert_eq!(sorted("Rust WEB"), [0, 2]);
+    }
+
+    #[test]
+    fn cjk_keywords() {
+        let index = index(&[
+            ("", "猫が好き"),
+            ("", "犬が好き"),
+            ("", "猫"),
+            ("", "バグを直す"),
+            ("", "ハクを直す"),
+        ]);
+        let sorted = |keyword| {
+            let mut result = search(&index, keyword);
+            result.sort_unstable();
+            result
+        };
+
+        assert_eq!(sorted("猫"), [0, 2]);
+        assert_eq!(sorted("好き"), [0, 1]);
+        assert_eq!(sorted("が好き"), [0, 1]);
+        assert_eq!(sorted("猫が"), [0]);
+        assert_eq!(sorted("き"), [0, 1]);
+        assert_eq!(sorted("バグ"), [3]);
+        assert_eq!(sorted("ハク"), [4]);
+        assert!(index.query_terms("猫").contains("猫が"));
+    }
+}
@@ -0,0 +1,292 @@
+use super::{ContentType, TimeRange};
+use chrono::NaiveDate;
+use std::borrow::Cow;
+
+pub struct SearchQuery {
+    pub filters: Vec<SearchFilter>,
+    pub sort: Option<SearchSort>,
+    pub errors: Vec<String>,
+}
+
+impl SearchQuery {
+    pub fn from_uri_query(query: Option<&str>) -> Self {
+        let mut filters = Vec::new();
+        let mut errors = Vec::new();
+
+        let mut authors = Vec::new();
+        let mut content_types = Vec::new();
+        let mut sort = None;
+        let mut time_range = None;
+        let mut from = None;
+        let mut to = None;
+
+        let pairs = form_urlencoded::parse(query.unwrap_or("").as_bytes());
+
+        for (key, value) in pairs {
+            let value = value.trim();
+
+            match key.as_ref() {
+                "keyword" if !value.is_empty() => {
+                    filters.push(SearchFilter::Keyword(value.to_owned()));
+                }
+                "author" if !value.is_empty() => {
+                    authors.push(value.to_owned());
+                }
+                "tag" if !value.is_empty() => {
+                    filters.push(SearchFilter::Tag(value.to_owned()));
+                }
+                "sort" => match SearchSort::parse(value) {
+                    Some(_) if sort.is_some() => {
+                        errors.push(String::from("Only one `sort` can be given"))
+                    }
+                    Some(parsed) => sort = Some(parsed),
+                    None => errors.push(format!(
+                        "Unknown sort `{}`: expected `newest`, `oldest`, `modified`, `title` or `relevance`",
+                        value
+                    )),
+                },
+                "type" => match ContentType::parse(value) {
+                    Some(content_type) => content_types.push(content_type),
+                    None => errors.push(format!(
+                        "Unknown content type `{}`: expected `post` or `page`",
+                        value
+                    )),
+                },
+                "time_range" => {
+                    let parsed = value
+                        .split_once('-')
+                        .and_then(|(from, to)| Some((from.parse().ok()?, to.parse().ok()?)))
+                        .and_then(|(from, to)| TimeRange::from_timestamps(from, to));
+
+                    match parsed {
+                        Some(_) if time_range.is_some() => {
+                            errors.push(String::from("Only one `time_range` can be given"))
+                        }
+                        Some(parsed) => time_range = Some(parsed),
+                        None => errors.push(format!(
+                            "Invalid time range `{}`: expected START_TIMESTAMP-END_TIMESTAMP",
+                            value
+                        )),
+                    }
+                }
+                "from" | "to" => {
+                    let date = if key == "from" { &mut from } else { &mut to };
+
+                    match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+                        Ok(_) if date.is_some() => {
+                            errors.push(format!("Only one `{}` date can be given", key))
+                        }
+                        Ok(parsed) => *date = Some(parsed),
+                        Err(_) => errors.push(format!(
+                            "Invalid `{}` date `{}`: expected YYYY-MM-DD",
+                            key, value
+                        )),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if !authors.is_empty() {
+            filters.push(SearchFilter::Author(authors));
+        }
+
+        if !content_types.is_empty() {
+            filters.push(SearchFilter::Type(content_types));
+        }
+
+        if let Some(time_range) = time_range {
+            filters.push(SearchFilter::TimeRange(time_range));
+        }
+
+        if from.is_some() || to.is_some() {
+            match TimeRange::from_dates(from, to) {
+                Some(time_range) => filters.push(SearchFilter::TimeRange(time_range)),
+                None => errors.push(String::from("The `from` date is after the `to` date")),
+            }
+        }
+
+        Self {
+            filters,
+            sort,
+            errors,
+        }
+    }
+
+    pub fn keywords(&self) -> impl Iterator<Item = &str> {
+        self.filters.iter().filter_map(|filter| match filter {
+            SearchFilter::Keyword(keyword) => Some(keyword.as_str()),
+            _ => None,
+        })
+    }
+}
+
+pub enum SearchFilter {
+    Keyword(String),
+    TimeRange(TimeRange),
+    Author(Vec<String>),
+    Type(Vec<ContentType>),
+    Tag(String),
+}
+
+impl SearchFilter {
+    pub fn to_breadcrumb(&self) -> (&str, Cow<'_, str>) {
+        match self {
+            Self::Keyword(keyword) => ("Keyword", Cow::Borrowed(keyword)),
+            Self::TimeRange(time_range) => ("Time Range", Cow::Owned(time_range.to_string())),
+            Self::Author(authors) => ("Author", Cow::Owned(authors.join(", "))),
+            Self::Tag(tag) => ("Tag", Cow::Borrowed(tag)),
+            Self::Type(content_types) => (
+                "Type",
+                Cow::Owned(
+                    content_types
+                        .iter()
+                        .map(|content_type| content_type.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                ),
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchSort {
+    Newest,
+    Oldest,
+    Modified,
+    Title,
+    Relevance,
+}
+
+impl SearchSort {
+    pub fn parse(str: &str) -> Option<Self> {
+        match str {
+            "newest" => Some(Self::Newest),
+            "oldest" => Some(Self::Oldest),
+            "modified" => Some(Self::Modified),
+            "title" => Some(Self::Title),
+            "relevance" => Some(Self::Relevance),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    fn parse(query: &str) -> SearchQuery {
+        Config::init_for_test();
+        SearchQuery::from_uri_query(Some(query))
+    }
+
+    fn filters(query: &SearchQuery) -> Vec<String> {
+        query
+            .filters
+            .iter()
+            .map(|filter| {
+                let (name, value) = filter.to_breadcrumb();
+                format!("{}: {}", name, value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decoding() {
+        let query = parse("keyword=rust+web&keyword=%22caf%C3%A9%22%20-go&tag=a%26b&author=+Jane+");
+
+        assert_eq!(
+            query.keywords().collect::<Vec<_>>(),
+            ["rust web", r#""café" -go"#]
+        );
+        assert_eq!(
+            filters(&query),
+            [
+                "Keyword: rust web",
+                r#"Keyword: "café" -go"#,
+                "Tag: a&b",
+                "Author: Jane"
+            ]
+        );
+        assert!(query.errors.is_empty());
+    }
+
+    #[test]
+    fn empty_values() {
+        let query = parse("keyword=&keyword=+&author=&tag=&page=2&unknown=1");
+
+        assert!(query.filters.is_empty());
+        assert!(query.sort.is_none());
+        assert!(query.errors.is_empty());
+        assert!(SearchQuery::from_uri_query(None).filters.is_empty());
+    }
+
+    #[test]
+    fn grouped_filters() {
+        let query = parse("author=Jane&type=post&author=Joe&type=page&sort=title");
+
+        assert_eq!(filters(&query), ["Author: Jane, Joe", "Type: post, page"]);
+        assert_eq!(query.sort, Some(SearchSort::Title));
+        assert!(query.errors.is_empty());
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(
+            filters(&parse("from=2021-02-03&to=2021-02-04")),
+            ["Time Range: From 2021-02-03 00:00:00 UTC To 2021-02-04 23:59:59 UTC"]
+        );
+        assert_eq!(
+            filters(&parse("from=2021-02-03")),
+            ["Time Range: From 2021-02-03 00:00:00 UTC To 9999-12-31 23:59:59 UTC"]
+        );
+        assert_eq!(
+            filters(&parse("to=2021-02-03")),
+            ["Time Range: From 1970-01-01 00:00:00 UTC To 2021-02-03 23:59:59 UTC"]
+        );
+        assert_eq!(
+            filters(&parse("time_range=0-86400")),
+            ["Time Range: From 1970-01-01 00:00:00 UTC To 1970-01-02 00:00:00 UTC"]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let query = parse(concat!(
+            "sort=newest&sort=oldest&sort=best&type=draft&",
+            "time_range=2-1&time_range=abc&",
+            "from=2021-13-01&from=yesterday&to=2021-02-30"
+        ));
+
+        assert!(query.filters.is_empty());
+        assert_eq!(query.sort, Some(SearchSort::Newest));
+        assert_eq!(
+            query.errors,
+            [
+                "Only one `sort` can be given",
+                "Unknown sort `best`: expected `newest`, `oldest`, `modified`, `title` or `relevance`",
+                "Unknown content type `draft`: expected `post` or `page`",
+                "Invalid time range `2-1`: expected START_TIMESTAMP-END_TIMESTAMP",
+                "Invalid time range `abc`: expected START_TIMESTAMP-END_TIMESTAMP",
+                "Invalid `from` date `2021-13-01`: expected YYYY-MM-DD",
+                "Invalid `from` date `yesterday`: expected YYYY-MM-DD",
+                "Invalid `to` date `2021-02-30`: expected YYYY-MM-DD"
+            ]
+        );
+
+        assert_eq!(
+            parse("from=2021-01-01&from=2021-01-02").errors,
+            ["Only one `from` date can be given"]
+        );
+        assert_eq!(
+            parse("time_range=0-1&time_range=0-2").errors,
+            ["Only one `time_range` can be given"]
+        );
+
+        let query = parse("from=2021-02-04&to=2021-02-03");
+        assert!(query.filters.is_empty());
+        assert_eq!(query.errors, ["The `from` date is after the `to` date"]);
+    }
+}
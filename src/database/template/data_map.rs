@@ -1,54 +1,65 @@
-use super::{markdown, parameter::*};
+use super::{date, highlight, html, parameter::*, snippet::Snippet};
 use crate::{
-    database::{Database, ListInfo, Page, Post, PostFilter, TimeRange},
+    database::{
+        Content, Database, FacetKind, Facets, ListInfo, MenuItem, Page, Params, Post, SearchQuery,
+        TimeRange,
+    },
     Config,
 };
-use chrono::DateTime;
+use chrono::{DateTime, NaiveDate};
 use chrono_tz::Tz;
 use hyper::{Body, Request, Uri};
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
 
 pub struct SiteDataMap<'d> {
     url: Cow<'d, str>,
     name: Cow<'d, str>,
     description: Cow<'d, str>,
-    page_list: Cow<'d, str>,
-    recent_posts: Cow<'d, str>,
+    page_list: Vec<ItemDataMap<'d>>,
+    recent_posts: Vec<ItemDataMap<'d>>,
+    menus: HashMap<&'d str, Vec<ItemDataMap<'d>>>,
+    params: &'d Params,
 }
 
 impl<'d> SiteDataMap<'d> {
-    pub fn from_config_and_db(db: &Database) -> Self {
-        let mut page_list = String::from(r#"<ol id="page_list">"#);
-        db.pages.data.iter().for_each(|page| {
-            page_list.push_str(r#"<li><a herf=""#);
-            page_list.push_str(&page.url);
-            page_list.push_str(r#"">"#);
-            page_list.push_str(&page.title);
-            page_list.push_str(r#"</a></li>"#);
-        });
-        page_list.push_str(r#"</ol>"#);
+    pub fn from_config_and_db(db: &'d Database, req: &Request<Body>) -> Self {
+        let site_url = &Config::read().site.url;
+        let is_current = |url: &str| url[site_url.len()..] == *req.uri().path();
+
+        let page_list = db
+            .pages
+            .data
+            .iter()
+            .map(|page| ItemDataMap::new(&page.title, &page.url, is_current(&page.url)))
+            .collect();
 
-        let mut recent_posts = String::from(r#"<ol id="recent_posts">"#);
-        db.posts
+        let recent_posts = db
+            .posts
             .data
             .iter()
             .rev()
             .take(Config::read().site.list_posts_count)
-            .for_each(|post| {
-                recent_posts.push_str(r#"<li><a herf=""#);
-                recent_posts.push_str(&post.url);
-                recent_posts.push_str(r#"">"#);
-                recent_posts.push_str(&post.title);
-                recent_posts.push_str(r#"</a></li>"#);
-            });
-        recent_posts.push_str(r#"</ol>"#);
+            .map(|post| ItemDataMap::new(&post.title, &post.url, is_current(&post.url)))
+            .collect();
+
+        let menus = db
+            .menus
+            .menus
+            .iter()
+            .map(|(name, items)| (name.as_str(), Self::gen_menu(items, req.uri())))
+            .collect();
 
         Self {
-            url: Cow::Borrowed(&Config::read().site.url),
+            url: Cow::Borrowed(site_url),
             name: Cow::Borrowed(&Config::read().site.name),
             description: Cow::Borrowed(Config::read().site.description.as_deref().unwrap_or("")),
-            page_list: Cow::Owned(page_list),
-            recent_posts: Cow::Owned(recent_posts),
+            page_list,
+            recent_posts,
+            menus,
+            params: &db.params,
         }
     }
 
@@ -57,8 +68,62 @@ impl<'d> SiteDataMap<'d> {
             SiteParameter::Url => Cow::Borrowed(&self.url),
             SiteParameter::Name => Cow::Borrowed(&self.name),
             SiteParameter::Description => Cow::Borrowed(&self.description),
-            SiteParameter::PageList => Cow::Borrowed(&self.page_list),
-            SiteParameter::RecentPosts => Cow::Borrowed(&self.recent_posts),
+            SiteParameter::PageList => Cow::Owned(ItemDataMap::gen_list(
+                "ol",
+                "page_list",
+                "current",
+                &self.page_list,
+            )),
+            SiteParameter::RecentPosts => Cow::Owned(ItemDataMap::gen_list(
+                "ol",
+                "recent_posts",
+                "current",
+                &self.recent_posts,
+            )),
+            SiteParameter::Menu(name) => match self.menus.get(name.as_str()) {
+                Some(items) => Cow::Owned(ItemDataMap::gen_list(
+                    "ol",
+                    &format!("menu_{}", name),
+                    "active",
+                    items,
+                )),
+                None => Cow::Borrowed(""),
+            },
+            SiteParameter::HighlightCss => {
+                Cow::Owned(format!("<style>{}</style>", highlight::css()))
+            }
+            SiteParameter::Param(key) => Cow::Borrowed(self.params.get(key).unwrap_or("")),
+        }
+    }
+
+    pub fn get_list(&self, param: &SiteParameter) -> Vec<ItemDataMap<'d>> {
+        match param {
+            SiteParameter::PageList => self.page_list.clone(),
+            SiteParameter::RecentPosts => self.recent_posts.clone(),
+            SiteParameter::Menu(name) => self.menus.get(name.as_str()).cloned().unwrap_or_default(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn gen_menu(items: &'d [MenuItem], url: &Uri) -> Vec<ItemDataMap<'d>> {
+        items
+            .iter()
+            .map(|item| ItemDataMap::new(&item.title, &item.url, item.is_active(url.path())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+impl<'d> SiteDataMap<'d> {
+    pub fn for_test(params: &'d Params) -> Self {
+        Self {
+            url: Cow::Borrowed(&Config::read().site.url),
+            name: Cow::Borrowed(&Config::read().site.name),
+            description: Cow::Borrowed(""),
+            page_list: Vec::new(),
+            recent_posts: Vec::new(),
+            menus: HashMap::new(),
+            params,
         }
     }
 }
@@ -67,22 +132,26 @@ pub struct DocumentDataMap<'d> {
     title: Cow<'d, str>,
     url: &'d Uri,
     breadcrumbs: Cow<'d, str>,
-    page_nav: Cow<'d, str>,
+    page_nav: PageNav,
     current_page_num_in_list: usize,
     total_num_of_articles_in_list: usize,
+    errors: Cow<'d, str>,
+    facets: HashMap<FacetKind, Vec<ItemDataMap<'d>>>,
 }
 
 impl<'d> DocumentDataMap<'d> {
-    const EMPTY_PAGE_NAV: &'static str = "";
+    const EMPTY_ERRORS: &'static str = "";
 
     pub fn from_page(req: &'d Request<Body>, page: &'d Page) -> Self {
         Self {
             title: Cow::Borrowed(&page.title),
             url: req.uri(),
-            breadcrumbs: Cow::Owned(format!("<span>Page: {}</span>", page.title)),
-            page_nav: Cow::Borrowed(Self::EMPTY_PAGE_NAV),
+            breadcrumbs: Cow::Owned(format!("<span>Page: {}</span>", html::escape(&page.title))),
+            page_nav: PageNav::default(),
             current_page_num_in_list: 0,
             total_num_of_articles_in_list: 0,
+            errors: Cow::Borrowed(Self::EMPTY_ERRORS),
+            facets: HashMap::new(),
         }
     }
 
@@ -90,10 +159,12 @@ impl<'d> DocumentDataMap<'d> {
         Self {
             title: Cow::Borrowed(&post.title),
             url: req.uri(),
-            breadcrumbs: Cow::Owned(format!("<span>Post: {}</span>", post.title)),
-            page_nav: Cow::Borrowed(Self::EMPTY_PAGE_NAV),
+            breadcrumbs: Cow::Owned(format!("<span>Post: {}</span>", html::escape(&post.title))),
+            page_nav: PageNav::default(),
             current_page_num_in_list: 0,
             total_num_of_articles_in_list: 0,
+            errors: Cow::Borrowed(Self::EMPTY_ERRORS),
+            facets: HashMap::new(),
         }
     }
 
@@ -102,34 +173,53 @@ impl<'d> DocumentDataMap<'d> {
             title: Cow::Borrowed(""),
             url: req.uri(),
             breadcrumbs: Cow::Borrowed("<span></span>"),
-            page_nav: Cow::Owned(Self::gen_page_nav(req.uri(), &list_info)),
+            page_nav: PageNav::new(req.uri(), &list_info),
             current_page_num_in_list: list_info.current_page_num_in_list,
             total_num_of_articles_in_list: list_info.total_num_of_articles_in_list,
+            errors: Cow::Borrowed(Self::EMPTY_ERRORS),
+            facets: HashMap::new(),
         }
     }
 
     pub fn from_search(
         req: &'d Request<Body>,
-        filters: Vec<PostFilter>,
+        query: &SearchQuery,
+        facets: &Facets,
         list_info: ListInfo,
     ) -> Self {
         let mut breadcrumbs = String::new();
-        filters.iter().for_each(|filter| {
+        query.filters.iter().for_each(|filter| {
             breadcrumbs.push_str(r#"<span>"#);
             let (breadcrumb_type, breadcrumb_value) = filter.to_breadcrumb();
             breadcrumbs.push_str(breadcrumb_type);
             breadcrumbs.push_str(r#": "#);
-            breadcrumbs.push_str(&breadcrumb_value);
+            html::push_escaped(&mut breadcrumbs, &breadcrumb_value);
             breadcrumbs.push_str(r#"</span>"#);
         });
 
+        let errors = if !query.errors.is_empty() {
+            let mut errors = String::from(r#"<ul id="errors">"#);
+            query.errors.iter().for_each(|error| {
+                errors.push_str(r#"<li>"#);
+                html::push_escaped(&mut errors, error);
+                errors.push_str(r#"</li>"#);
+            });
+            errors.push_str(r#"</ul>"#);
+
+            Cow::Owned(errors)
+        } else {
+            Cow::Borrowed(Self::EMPTY_ERRORS)
+        };
+
         Self {
             title: Cow::Borrowed("Search"),
             url: req.uri(),
             breadcrumbs: Cow::Owned(breadcrumbs),
-            page_nav: Cow::Owned(Self::gen_page_nav(req.uri(), &list_info)),
+            page_nav: PageNav::new(req.uri(), &list_info),
             current_page_num_in_list: list_info.current_page_num_in_list,
             total_num_of_articles_in_list: list_info.total_num_of_articles_in_list,
+            errors,
+            facets: Self::gen_facets(req.uri(), facets),
         }
     }
 
@@ -137,10 +227,12 @@ impl<'d> DocumentDataMap<'d> {
         Self {
             title: Cow::Owned(format!("Author: {}", author)),
             url: req.uri(),
-            breadcrumbs: Cow::Owned(format!("<span>Author: {}</span>", author)),
-            page_nav: Cow::Owned(Self::gen_page_nav(req.uri(), &list_info)),
+            breadcrumbs: Cow::Owned(format!("<span>Author: {}</span>", html::escape(author))),
+            page_nav: PageNav::new(req.uri(), &list_info),
             current_page_num_in_list: list_info.current_page_num_in_list,
             total_num_of_articles_in_list: list_info.total_num_of_articles_in_list,
+            errors: Cow::Borrowed(Self::EMPTY_ERRORS),
+            facets: HashMap::new(),
         }
     }
 
@@ -155,9 +247,11 @@ impl<'d> DocumentDataMap<'d> {
             title: Cow::Owned(time_range.to_string()),
             url: req.uri(),
             breadcrumbs: Cow::Owned(breadcrumbs),
-            page_nav: Cow::Owned(Self::gen_page_nav(req.uri(), &list_info)),
+            page_nav: PageNav::new(req.uri(), &list_info),
             current_page_num_in_list: list_info.current_page_num_in_list,
             total_num_of_articles_in_list: list_info.total_num_of_articles_in_list,
+            errors: Cow::Borrowed(Self::EMPTY_ERRORS),
+            facets: HashMap::new(),
         }
     }
 
@@ -166,9 +260,11 @@ impl<'d> DocumentDataMap<'d> {
             title: Cow::Borrowed("Not Found"),
             url: req.uri(),
             breadcrumbs: Cow::Borrowed("<span>Not Found</span>"),
-            page_nav: Cow::Borrowed(Self::EMPTY_PAGE_NAV),
+            page_nav: PageNav::default(),
             current_page_num_in_list: 0,
             total_num_of_articles_in_list: 0,
+            errors: Cow::Borrowed(Self::EMPTY_ERRORS),
+            facets: HashMap::new(),
         }
     }
 
@@ -177,66 +273,169 @@ impl<'d> DocumentDataMap<'d> {
             DocumentParameter::Title => Cow::Borrowed(&self.title),
             DocumentParameter::Url => Cow::Owned(self.url.to_string()),
             DocumentParameter::Breadcrumb => Cow::Borrowed(&self.breadcrumbs),
-            DocumentParameter::PageNav => Cow::Borrowed(&self.page_nav),
+            DocumentParameter::PageNav => Cow::Owned(self.page_nav.to_html()),
+            DocumentParameter::PrevPage => {
+                Cow::Borrowed(self.page_nav.prev.as_deref().unwrap_or(""))
+            }
+            DocumentParameter::NextPage => {
+                Cow::Borrowed(self.page_nav.next.as_deref().unwrap_or(""))
+            }
             DocumentParameter::CurrentPageNumInList => {
                 Cow::Owned(self.current_page_num_in_list.to_string())
             }
             DocumentParameter::TotalNumOfArticleInList => {
                 Cow::Owned(self.total_num_of_articles_in_list.to_string())
             }
+            DocumentParameter::Errors => Cow::Borrowed(&self.errors),
+            DocumentParameter::Facet(kind) => match self.facets.get(kind) {
+                Some(items) => Cow::Owned(ItemDataMap::gen_list(
+                    "ul",
+                    &format!("facet_{}", kind.as_str()),
+                    "current",
+                    items,
+                )),
+                None => Cow::Borrowed(""),
+            },
+        }
+    }
+
+    pub fn get_list(&self, param: &DocumentParameter) -> Vec<ItemDataMap<'d>> {
+        match param {
+            DocumentParameter::PageNav => self.page_nav.items.clone(),
+            DocumentParameter::Facet(kind) => self.facets.get(kind).cloned().unwrap_or_default(),
+            _ => unreachable!(),
         }
     }
 
-    fn gen_page_nav(url: &Uri, list_info: &ListInfo) -> String {
+    fn gen_facets(url: &Uri, facets: &Facets) -> HashMap<FacetKind, Vec<ItemDataMap<'d>>> {
+        let date_keys = ["time_range", "from", "to"];
+
+        let authors = facets.authors.iter().map(|(author, count)| {
+            let url = Self::gen_refined_url(url, &["author"], &[("author", author)]);
+            ItemDataMap::with_count(author.to_string(), url, *count)
+        });
+
+        let years = facets.years.iter().map(|(year, count)| {
+            let from = format!("{}-01-01", year);
+            let to = format!("{}-12-31", year);
+            let url = Self::gen_refined_url(url, &date_keys, &[("from", &from), ("to", &to)]);
+            ItemDataMap::with_count(year.to_string(), url, *count)
+        });
+
+        let months = facets.months.iter().map(|((year, month), count)| {
+            let last_day = (28..=31)
+                .rev()
+                .find(|day| NaiveDate::from_ymd_opt(*year, *month, *day).is_some())
+                .unwrap_or(28);
+            let from = format!("{}-{:02}-01", year, month);
+            let to = format!("{}-{:02}-{:02}", year, month, last_day);
+            let url = Self::gen_refined_url(url, &date_keys, &[("from", &from), ("to", &to)]);
+            ItemDataMap::with_count(format!("{}-{:02}", year, month), url, *count)
+        });
+
+        let tags = facets.tags.iter().map(|(tag, count)| {
+            let url = Self::gen_refined_url(url, &[], &[("tag", tag)]);
+            ItemDataMap::with_count(tag.to_string(), url, *count)
+        });
+
+        let mut result = HashMap::new();
+        result.insert(FacetKind::Author, authors.collect());
+        result.insert(FacetKind::Year, years.collect());
+        result.insert(FacetKind::Month, months.collect());
+        result.insert(FacetKind::Tag, tags.collect());
+        result
+    }
+
+    fn gen_refined_url(url: &Uri, removed_keys: &[&str], added: &[(&str, &str)]) -> String {
+        let mut query = form_urlencoded::Serializer::new(String::new());
+
+        form_urlencoded::parse(url.query().unwrap_or("").as_bytes())
+            .filter(|(key, value)| {
+                key != "page"
+                    && !removed_keys.contains(&key.as_ref())
+                    && !added.contains(&(key.as_ref(), value.as_ref()))
+            })
+            .for_each(|(key, value)| {
+                query.append_pair(&key, &value);
+            });
+        query.extend_pairs(added);
+
+        format!("{}?{}", url.path(), query.finish())
+    }
+}
+
+#[derive(Default)]
+struct PageNav {
+    items: Vec<ItemDataMap<'static>>,
+    prev: Option<String>,
+    next: Option<String>,
+}
+
+impl PageNav {
+    fn new(url: &Uri, list_info: &ListInfo) -> Self {
         let url = url.to_string();
         let url_part_front = &url[..list_info.page_num_pos_in_url_start_idx];
         let url_part_back = &url[list_info.page_num_pos_in_url_end_idx..];
 
+        let page_url = |page_num: usize| {
+            format!(
+                "{}{}{}{}",
+                url_part_front,
+                list_info.param_key(),
+                page_num,
+                url_part_back
+            )
+        };
+
+        let items = (1..=list_info.total_page.max(list_info.current_page_num_in_list))
+            .map(|page_num| {
+                if page_num == list_info.current_page_num_in_list {
+                    ItemDataMap::owned(page_num.to_string(), url.clone(), true)
+                } else {
+                    ItemDataMap::owned(page_num.to_string(), page_url(page_num), false)
+                }
+            })
+            .collect();
+
+        let prev = if list_info.current_page_num_in_list != 1 {
+            Some(page_url(list_info.current_page_num_in_list - 1))
+        } else {
+            None
+        };
+
+        let next = if list_info.current_page_num_in_list < list_info.total_page {
+            Some(page_url(list_info.current_page_num_in_list + 1))
+        } else {
+            None
+        };
+
+        Self { items, prev, next }
+    }
+
+    fn to_html(&self) -> String {
+        if self.items.is_empty() {
+            return String::new();
+        }
+
         let mut result = String::from(r#"<ol id="page_nav">"#);
 
-        if list_info.current_page_num_in_list != 1 {
-            result.push_str(r#"<li id="prev"><a herf=""#);
-            result.push_str(url_part_front);
-            result.push_str(list_info.param_key());
-            result.push_str(&(list_info.current_page_num_in_list - 1).to_string());
-            result.push_str(url_part_back);
+        if let Some(prev) = &self.prev {
+            result.push_str(r#"<li id="prev"><a href=""#);
+            html::push_escaped(&mut result, prev);
             result.push_str(r#"">prev</a></li>"#);
         }
 
-        for page_num in 1..list_info.current_page_num_in_list {
-            result.push_str(r#"<li id="current"><a herf=""#);
-            result.push_str(url_part_front);
-            result.push_str(list_info.param_key());
-            result.push_str(&page_num.to_string());
-            result.push_str(url_part_back);
+        self.items.iter().for_each(|item| {
+            result.push_str(r#"<li id="current"><a href=""#);
+            html::push_escaped(&mut result, &item.url);
             result.push_str(r#"">"#);
-            result.push_str(&page_num.to_string());
+            html::push_escaped(&mut result, &item.title);
             result.push_str(r#"</a></li>"#);
-        }
-
-        result.push_str(r#"<li id="current"><a herf=""#);
-        result.push_str(&url);
-        result.push_str(r#"">"#);
-        result.push_str(&(list_info.current_page_num_in_list).to_string());
-        result.push_str(r#"</a></li>"#);
-
-        if list_info.current_page_num_in_list != list_info.total_page {
-            for page_num in list_info.current_page_num_in_list + 1..=list_info.total_page {
-                result.push_str(r#"<li id="current"><a herf=""#);
-                result.push_str(url_part_front);
-                result.push_str(list_info.param_key());
-                result.push_str(&page_num.to_string());
-                result.push_str(url_part_back);
-                result.push_str(r#"">"#);
-                result.push_str(&page_num.to_string());
-                result.push_str(r#"</a></li>"#);
-            }
+        });
 
-            result.push_str(r#"<li id="next"><a herf=""#);
-            result.push_str(url_part_front);
-            result.push_str(list_info.param_key());
-            result.push_str(&(list_info.current_page_num_in_list + 1).to_string());
-            result.push_str(url_part_back);
+        if let Some(next) = &self.next {
+            result.push_str(r#"<li id="next"><a href=""#);
+            html::push_escaped(&mut result, next);
             result.push_str(r#"">next</a></li>"#);
         }
 
@@ -250,9 +449,13 @@ pub struct PageDataMap<'d> {
     title: Cow<'d, str>,
     url: Cow<'d, str>,
     content: Cow<'d, str>,
+    toc: Cow<'d, str>,
     author: Cow<'d, str>,
+    word_count: usize,
+    reading_time: usize,
     create_time: &'d DateTime<Tz>,
     modify_time: &'d DateTime<Tz>,
+    params: &'d Params,
 }
 
 impl<'d> PageDataMap<'d> {
@@ -260,10 +463,22 @@ impl<'d> PageDataMap<'d> {
         Self {
             title: Cow::Borrowed(&page.title),
             url: Cow::Borrowed(&page.url),
-            content: Cow::Owned(markdown::md_to_html(&page.content)),
+            content: Cow::Borrowed(&page.html),
+            toc: Cow::Borrowed(&page.toc),
             author: Cow::Borrowed(page.author.as_deref().unwrap_or("Anonymous")),
+            word_count: page.word_count,
+            reading_time: page.reading_time,
             create_time: &page.create_time,
             modify_time: &page.modify_time,
+            params: &page.params,
+        }
+    }
+
+    pub fn get_time(&self, param: &PageParameter) -> &'d DateTime<Tz> {
+        match param {
+            PageParameter::CreateTime => self.create_time,
+            PageParameter::ModifyTime => self.modify_time,
+            _ => unreachable!(),
         }
     }
 
@@ -272,9 +487,13 @@ impl<'d> PageDataMap<'d> {
             PageParameter::Title => Cow::Borrowed(&self.title),
             PageParameter::Url => Cow::Borrowed(&self.url),
             PageParameter::Content => Cow::Borrowed(&self.content),
+            PageParameter::Toc => Cow::Borrowed(&self.toc),
             PageParameter::Author => Cow::Borrowed(&self.author),
-            PageParameter::CreateTime => Cow::Owned(self.create_time.to_string()),
-            PageParameter::ModifyTime => Cow::Owned(self.modify_time.to_string()),
+            PageParameter::WordCount => Cow::Owned(self.word_count.to_string()),
+            PageParameter::ReadingTime => Cow::Owned(self.reading_time.to_string()),
+            PageParameter::CreateTime => Cow::Owned(date::format_default(self.create_time)),
+            PageParameter::ModifyTime => Cow::Owned(date::format_default(self.modify_time)),
+            PageParameter::Param(key) => Cow::Borrowed(self.params.get(key).unwrap_or("")),
         }
     }
 }
@@ -283,20 +502,92 @@ pub struct PostDataMap<'d> {
     title: Cow<'d, str>,
     url: Cow<'d, str>,
     content: Cow<'d, str>,
+    toc: Cow<'d, str>,
     author: Cow<'d, str>,
+    word_count: usize,
+    reading_time: usize,
     create_time: &'d DateTime<Tz>,
     modify_time: &'d DateTime<Tz>,
+    prev: Cow<'d, str>,
+    next: Cow<'d, str>,
+    series: Cow<'d, str>,
+    series_toc: Vec<ItemDataMap<'d>>,
+    series_prev: Cow<'d, str>,
+    series_next: Cow<'d, str>,
+    related: Vec<ItemDataMap<'d>>,
+    tags: Vec<ItemDataMap<'d>>,
+    params: &'d Params,
 }
 
 impl<'d> PostDataMap<'d> {
-    pub fn from_post(post: &'d Post) -> Self {
+    pub fn from_post(db: &'d Database, post_id: usize) -> Self {
+        let post = db.posts.get(post_id);
+
+        let (prev, next) = db.posts.get_neighbours(post_id);
+
+        let (series_toc, series_prev, series_next) = match post
+            .series
+            .as_deref()
+            .and_then(|series| db.posts.get_series(series))
+        {
+            Some(series) => {
+                let series_toc = series
+                    .iter()
+                    .map(|id| {
+                        let series_post = db.posts.get(*id);
+                        ItemDataMap::new(&series_post.title, &series_post.url, *id == post_id)
+                    })
+                    .collect();
+
+                let pos = series.iter().position(|id| *id == post_id).unwrap();
+                let series_prev = pos.checked_sub(1).map(|pos| db.posts.get(series[pos]));
+                let series_next = series.get(pos + 1).map(|id| db.posts.get(*id));
+
+                (
+                    series_toc,
+                    Self::gen_post_link("series_prev", series_prev),
+                    Self::gen_post_link("series_next", series_next),
+                )
+            }
+            None => (Vec::new(), Cow::Borrowed(""), Cow::Borrowed("")),
+        };
+
+        let related = post
+            .related
+            .iter()
+            .map(|id| {
+                let related_post = db.posts.get(*id);
+                ItemDataMap::new(&related_post.title, &related_post.url, false)
+            })
+            .collect();
+
         Self {
             title: Cow::Borrowed(&post.title),
             url: Cow::Borrowed(&post.url),
-            content: Cow::Owned(markdown::md_to_html(&post.content)),
+            content: Cow::Borrowed(&post.html),
+            toc: Cow::Borrowed(&post.toc),
             author: Cow::Borrowed(post.author.as_deref().unwrap_or("Anonymous")),
+            word_count: post.word_count,
+            reading_time: post.reading_time,
             create_time: &post.create_time,
             modify_time: &post.modify_time,
+            prev: Self::gen_post_link("prev_post", prev),
+            next: Self::gen_post_link("next_post", next),
+            series: Cow::Borrowed(post.series.as_deref().unwrap_or("")),
+            series_toc,
+            series_prev,
+            series_next,
+            related,
+            tags: post.tags.iter().map(|tag| ItemDataMap::tag(tag)).collect(),
+            params: &post.params,
+        }
+    }
+
+    pub fn get_time(&self, param: &PostParameter) -> &'d DateTime<Tz> {
+        match param {
+            PostParameter::CreateTime => self.create_time,
+            PostParameter::ModifyTime => self.modify_time,
+            _ => unreachable!(),
         }
     }
 
@@ -305,48 +596,253 @@ impl<'d> PostDataMap<'d> {
             PostParameter::Title => Cow::Borrowed(&self.title),
             PostParameter::Url => Cow::Borrowed(&self.url),
             PostParameter::Content => Cow::Borrowed(&self.content),
+            PostParameter::Toc => Cow::Borrowed(&self.toc),
             PostParameter::Author => Cow::Borrowed(&self.author),
-            PostParameter::CreateTime => Cow::Owned(self.create_time.to_string()),
-            PostParameter::ModifyTime => Cow::Owned(self.modify_time.to_string()),
+            PostParameter::WordCount => Cow::Owned(self.word_count.to_string()),
+            PostParameter::ReadingTime => Cow::Owned(self.reading_time.to_string()),
+            PostParameter::CreateTime => Cow::Owned(date::format_default(self.create_time)),
+            PostParameter::ModifyTime => Cow::Owned(date::format_default(self.modify_time)),
+            PostParameter::Prev => Cow::Borrowed(&self.prev),
+            PostParameter::Next => Cow::Borrowed(&self.next),
+            PostParameter::Series => Cow::Borrowed(&self.series),
+            PostParameter::SeriesToc if self.series_toc.is_empty() => Cow::Borrowed(""),
+            PostParameter::SeriesToc => Cow::Owned(ItemDataMap::gen_list(
+                "ol",
+                "series_toc",
+                "current",
+                &self.series_toc,
+            )),
+            PostParameter::SeriesPrev => Cow::Borrowed(&self.series_prev),
+            PostParameter::SeriesNext => Cow::Borrowed(&self.series_next),
+            PostParameter::Related if self.related.is_empty() => Cow::Borrowed(""),
+            PostParameter::Related => Cow::Owned(ItemDataMap::gen_list(
+                "ol",
+                "related_posts",
+                "current",
+                &self.related,
+            )),
+            PostParameter::Tags if self.tags.is_empty() => Cow::Borrowed(""),
+            PostParameter::Tags => {
+                Cow::Owned(ItemDataMap::gen_list("ul", "tags", "current", &self.tags))
+            }
+            PostParameter::Param(key) => Cow::Borrowed(self.params.get(key).unwrap_or("")),
+        }
+    }
+
+    pub fn get_list(&self, param: &PostParameter) -> Vec<ItemDataMap<'d>> {
+        match param {
+            PostParameter::SeriesToc => self.series_toc.clone(),
+            PostParameter::Related => self.related.clone(),
+            PostParameter::Tags => self.tags.clone(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn gen_post_link(id: &str, post: Option<&Post>) -> Cow<'d, str> {
+        match post {
+            Some(post) => Cow::Owned(format!(
+                r#"<a id="{}" href="{}">{}</a>"#,
+                id,
+                html::escape(&post.url),
+                html::escape(&post.title)
+            )),
+            None => Cow::Borrowed(""),
         }
     }
 }
 
 pub struct SummaryDataMap<'d> {
+    content_type: &'static str,
     title: Cow<'d, str>,
     url: Cow<'d, str>,
     summary: Cow<'d, str>,
+    has_more: bool,
     author: Cow<'d, str>,
+    word_count: usize,
+    reading_time: usize,
     create_time: &'d DateTime<Tz>,
     modify_time: &'d DateTime<Tz>,
+    tags: Vec<ItemDataMap<'d>>,
+    snippet: Option<Snippet>,
 }
 
 impl<'d> SummaryDataMap<'d> {
     pub fn from_post(post: &'d Post) -> Self {
-        let more_indicator_idx = post
-            .content
-            .find("<!--more-->")
-            .unwrap_or(post.content.len());
-        let post_summary = &post.content[..more_indicator_idx].trim_end();
+        Self::from_content(Content::Post(post))
+    }
 
+    pub fn from_content(content: Content<'d>) -> Self {
         Self {
-            title: Cow::Borrowed(&post.title),
-            url: Cow::Borrowed(&post.url),
-            summary: Cow::Owned(markdown::md_to_html(post_summary)),
-            author: Cow::Borrowed(post.author.as_deref().unwrap_or("Anonymous")),
-            create_time: &post.create_time,
-            modify_time: &post.modify_time,
+            content_type: content.content_type().as_str(),
+            title: Cow::Borrowed(content.title()),
+            url: Cow::Borrowed(content.url()),
+            summary: Cow::Borrowed(content.summary_html()),
+            has_more: content.has_more(),
+            author: Cow::Borrowed(content.author().unwrap_or("Anonymous")),
+            word_count: content.word_count(),
+            reading_time: content.reading_time(),
+            create_time: content.create_time(),
+            modify_time: content.modify_time(),
+            tags: content
+                .tags()
+                .iter()
+                .map(|tag| ItemDataMap::tag(tag))
+                .collect(),
+            snippet: None,
+        }
+    }
+
+    pub fn from_search_result(
+        db: &Database,
+        content: Content<'d>,
+        terms: &HashSet<String>,
+    ) -> Self {
+        let text = content.text();
+        let tokens = db.index.tokenizer().tokenize_with_spans(&text);
+
+        Self {
+            snippet: Some(Snippet::generate(&text, &tokens, terms)),
+            ..Self::from_content(content)
+        }
+    }
+
+    pub fn get_time(&self, param: &SummaryParameter) -> &'d DateTime<Tz> {
+        match param {
+            SummaryParameter::CreateTime => self.create_time,
+            SummaryParameter::ModifyTime => self.modify_time,
+            _ => unreachable!(),
         }
     }
 
     pub fn get(&'d self, param: &SummaryParameter) -> Cow<'d, str> {
         match param {
+            SummaryParameter::Type => Cow::Borrowed(self.content_type),
             SummaryParameter::Title => Cow::Borrowed(&self.title),
             SummaryParameter::Url => Cow::Borrowed(&self.url),
             SummaryParameter::Summary => Cow::Borrowed(&self.summary),
+            SummaryParameter::HasMore if self.has_more => Cow::Borrowed("true"),
+            SummaryParameter::HasMore => Cow::Borrowed(""),
             SummaryParameter::Author => Cow::Borrowed(&self.author),
-            SummaryParameter::CreateTime => Cow::Owned(self.create_time.to_string()),
-            SummaryParameter::ModifyTime => Cow::Owned(self.modify_time.to_string()),
+            SummaryParameter::WordCount => Cow::Owned(self.word_count.to_string()),
+            SummaryParameter::ReadingTime => Cow::Owned(self.reading_time.to_string()),
+            SummaryParameter::CreateTime => Cow::Owned(date::format_default(self.create_time)),
+            SummaryParameter::ModifyTime => Cow::Owned(date::format_default(self.modify_time)),
+            SummaryParameter::Snippet => match &self.snippet {
+                Some(snippet) => Cow::Borrowed(&snippet.html),
+                None => Cow::Borrowed(&self.summary),
+            },
+            SummaryParameter::MatchCount => Cow::Owned(
+                self.snippet
+                    .as_ref()
+                    .map_or(0, |snippet| snippet.match_count)
+                    .to_string(),
+            ),
+            SummaryParameter::Tags if self.tags.is_empty() => Cow::Borrowed(""),
+            SummaryParameter::Tags => {
+                Cow::Owned(ItemDataMap::gen_list("ul", "tags", "current", &self.tags))
+            }
+        }
+    }
+
+    pub fn get_list(&self, param: &SummaryParameter) -> Vec<ItemDataMap<'d>> {
+        match param {
+            SummaryParameter::Tags => self.tags.clone(),
+            _ => unreachable!(),
         }
     }
 }
+
+#[derive(Clone)]
+pub struct ItemDataMap<'d> {
+    title: Cow<'d, str>,
+    url: Cow<'d, str>,
+    is_current: bool,
+    count: Option<usize>,
+}
+
+impl<'d> ItemDataMap<'d> {
+    fn new(title: &'d str, url: &'d str, is_current: bool) -> Self {
+        Self {
+            title: Cow::Borrowed(title),
+            url: Cow::Borrowed(url),
+            is_current,
+            count: None,
+        }
+    }
+
+    fn owned(title: String, url: String, is_current: bool) -> Self {
+        Self {
+            title: Cow::Owned(title),
+            url: Cow::Owned(url),
+            is_current,
+            count: None,
+        }
+    }
+
+    fn with_count(title: String, url: String, count: usize) -> Self {
+        Self {
+            count: Some(count),
+            ..Self::owned(title, url, false)
+        }
+    }
+
+    fn tag(tag: &'d str) -> Self {
+        let query = form_urlencoded::Serializer::new(String::new())
+            .append_pair("tag", tag)
+            .finish();
+        let url = format!(
+            "{}{}?{}",
+            Config::read().site.url,
+            Config::read().url_patterns.search,
+            query
+        );
+
+        Self {
+            title: Cow::Borrowed(tag),
+            url: Cow::Owned(url),
+            is_current: false,
+            count: None,
+        }
+    }
+
+    pub fn get(&'d self, param: &ItemParameter) -> Cow<'d, str> {
+        match param {
+            ItemParameter::Title => Cow::Borrowed(&self.title),
+            ItemParameter::Url => Cow::Borrowed(&self.url),
+            ItemParameter::IsCurrent if self.is_current => Cow::Borrowed("true"),
+            ItemParameter::IsCurrent => Cow::Borrowed(""),
+            ItemParameter::Count => match self.count {
+                Some(count) => Cow::Owned(count.to_string()),
+                None => Cow::Borrowed(""),
+            },
+        }
+    }
+
+    fn gen_list(element: &str, id: &str, current_class: &str, items: &[Self]) -> String {
+        let mut result = format!(r#"<{} id="{}">"#, element, id);
+
+        items.iter().for_each(|item| {
+            if item.is_current {
+                result.push_str(r#"<li class=""#);
+                result.push_str(current_class);
+                result.push_str(r#""><a href=""#);
+            } else {
+                result.push_str(r#"<li><a href=""#);
+            }
+            html::push_escaped(&mut result, &item.url);
+            result.push_str(r#"">"#);
+            html::push_escaped(&mut result, &item.title);
+            result.push_str(r#"</a>"#);
+            if let Some(count) = item.count {
+                result.push_str(r#" <span class="count">"#);
+                result.push_str(&count.to_string());
+                result.push_str(r#"</span>"#);
+            }
+            result.push_str(r#"</li>"#);
+        });
+
+        result.push_str(&format!("</{}>", element));
+
+        result
+    }
+}
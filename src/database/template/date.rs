@@ -0,0 +1,438 @@
+use crate::{config::Language, Config};
+use anyhow::{bail, Result};
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, Datelike, Timelike,
+};
+use chrono_tz::Tz;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Debug)]
+pub enum DateFormat {
+    Pattern(String),
+    Rfc3339,
+    Rfc2822,
+    Relative,
+}
+
+impl DateFormat {
+    pub fn parse(str: &str) -> Result<Self> {
+        match str {
+            "iso" | "rfc3339" => Ok(Self::Rfc3339),
+            "rfc2822" => Ok(Self::Rfc2822),
+            "relative" => Ok(Self::Relative),
+            pattern => {
+                check_pattern(pattern)?;
+                Ok(Self::Pattern(pattern.to_owned()))
+            }
+        }
+    }
+
+    pub fn format(&self, time: &DateTime<Tz>) -> String {
+        match self {
+            Self::Pattern(pattern) => format_pattern(pattern, time),
+            Self::Rfc3339 => time.to_rfc3339(),
+            Self::Rfc2822 => time.to_rfc2822(),
+            Self::Relative => format_relative(time),
+        }
+    }
+}
+
+pub fn check_pattern(pattern: &str) -> Result<()> {
+    if StrftimeItems::new(pattern).any(|item| matches!(item, Item::Error)) {
+        bail!("Invalid date format: {}", pattern);
+    }
+
+    Ok(())
+}
+
+pub fn format_default(time: &DateTime<Tz>) -> String {
+    match &Config::read().site.date_format {
+        Some(pattern) => format_pattern(pattern, time),
+        None => time.to_string(),
+    }
+}
+
+fn format_pattern(pattern: &str, time: &DateTime<Tz>) -> String {
+    let language = Config::read().site.language;
+
+    if let Language::En = language {
+        return time.format(pattern).to_string();
+    }
+
+    time.format(&localize_pattern(pattern, time, language))
+        .to_string()
+}
+
+// Names are put into the pattern, and `%c`, `%x` and `%r` are expanded to make them localizable too
+fn localize_pattern(pattern: &str, time: &DateTime<Tz>, language: Language) -> String {
+    let mut localized = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            localized.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('B') => localized.push_str(month_name(language, time.month0())),
+            Some('b' | 'h') => localized.push_str(month_abbr(language, time.month0())),
+            Some('A') => localized.push_str(weekday_name(
+                language,
+                time.weekday().num_days_from_monday(),
+            )),
+            Some('a') => localized.push_str(weekday_abbr(
+                language,
+                time.weekday().num_days_from_monday(),
+            )),
+            Some('c') => localized.push_str(&localize_pattern(
+                date_time_pattern(language),
+                time,
+                language,
+            )),
+            Some('x') => {
+                localized.push_str(&localize_pattern(date_pattern(language), time, language))
+            }
+            Some('r') => localized.push_str(&localize_pattern("%I:%M:%S %p", time, language)),
+            Some(spec @ ('p' | 'P')) => match language {
+                Language::Ja if time.hour() < 12 => localized.push_str("午前"),
+                Language::Ja => localized.push_str("午後"),
+                Language::Zh if time.hour() < 12 => localized.push_str("上午"),
+                Language::Zh => localized.push_str("下午"),
+                _ => {
+                    localized.push('%');
+                    localized.push(spec);
+                }
+            },
+            Some(spec) => {
+                localized.push('%');
+                localized.push(spec);
+            }
+            None => localized.push('%'),
+        }
+    }
+
+    localized
+}
+
+fn format_relative(time: &DateTime<Tz>) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs() as i64);
+
+    relative(now - time.timestamp(), Config::read().site.language)
+}
+
+fn relative(seconds: i64, language: Language) -> String {
+    let (count, unit0) = match seconds.abs() {
+        0..=59 => {
+            return String::from(match language {
+                Language::En => "just now",
+                Language::De => "gerade eben",
+                Language::Es => "ahora mismo",
+                Language::Fr => "à l'instant",
+                Language::Ja => "たった今",
+                Language::Zh => "刚刚",
+            })
+        }
+        seconds @ 60..=3599 => (seconds / 60, 0),
+        seconds @ 3600..=86399 => (seconds / 3600, 1),
+        seconds @ 86400..=2591999 => (seconds / 86400, 2),
+        seconds @ 2592000..=31535999 => (seconds / 2592000, 3),
+        seconds => (seconds / 31536000, 4),
+    };
+
+    let unit = unit_name(language, unit0, count);
+
+    match (language, seconds > 0) {
+        (Language::En, true) => format!("{} {} ago", count, unit),
+        (Language::En, false) => format!("in {} {}", count, unit),
+        (Language::De, true) => format!("vor {} {}", count, unit),
+        (Language::De, false) => format!("in {} {}", count, unit),
+        (Language::Es, true) => format!("hace {} {}", count, unit),
+        (Language::Es, false) => format!("dentro de {} {}", count, unit),
+        (Language::Fr, true) => format!("il y a {} {}", count, unit),
+        (Language::Fr, false) => format!("dans {} {}", count, unit),
+        (Language::Ja, true) => format!("{}{}前", count, unit),
+        (Language::Ja, false) => format!("{}{}後", count, unit),
+        (Language::Zh, true) => format!("{}{}前", count, unit),
+        (Language::Zh, false) => format!("{}{}后", count, unit),
+    }
+}
+
+fn date_time_pattern(language: Language) -> &'static str {
+    match language {
+        Language::En => "%a %b %e %H:%M:%S %Y",
+        Language::De | Language::Es | Language::Fr => "%a %d %b %Y %H:%M:%S",
+        Language::Ja => "%Y年%m月%d日 %H時%M分%S秒",
+        Language::Zh => "%Y年%m月%d日 %A %H时%M分%S秒",
+    }
+}
+
+fn date_pattern(language: Language) -> &'static str {
+    match language {
+        Language::En => "%m/%d/%y",
+        Language::De => "%d.%m.%Y",
+        Language::Es => "%d/%m/%y",
+        Language::Fr => "%d/%m/%Y",
+        Language::Ja | Language::Zh => "%Y年%m月%d日",
+    }
+}
+
+// Units are minute, hour, day, month and year, as (singular, plural)
+fn unit_name(language: Language, unit0: usize, count: i64) -> &'static str {
+    let names = match language {
+        Language::En => [
+            ("minute", "minutes"),
+            ("hour", "hours"),
+            ("day", "days"),
+            ("month", "months"),
+            ("year", "years"),
+        ],
+        Language::De => [
+            ("Minute", "Minuten"),
+            ("Stunde", "Stunden"),
+            ("Tag", "Tagen"),
+            ("Monat", "Monaten"),
+            ("Jahr", "Jahren"),
+        ],
+        Language::Es => [
+            ("minuto", "minutos"),
+            ("hora", "horas"),
+            ("día", "días"),
+            ("mes", "meses"),
+            ("año", "años"),
+        ],
+        Language::Fr => [
+            ("minute", "minutes"),
+            ("heure", "heures"),
+            ("jour", "jours"),
+            ("mois", "mois"),
+            ("an", "ans"),
+        ],
+        Language::Ja => [
+            ("分", "分"),
+            ("時間", "時間"),
+            ("日", "日"),
+            ("か月", "か月"),
+            ("年", "年"),
+        ],
+        Language::Zh => [
+            ("分钟", "分钟"),
+            ("小时", "小时"),
+            ("天", "天"),
+            ("个月", "个月"),
+            ("年", "年"),
+        ],
+    };
+
+    let (singular, plural) = names[unit0];
+
+    if count == 1 {
+        singular
+    } else {
+        plural
+    }
+}
+
+fn month_name(language: Language, month0: u32) -> &'static str {
+    let names = match language {
+        Language::En => [
+            "January",
+            "February",
+            "March",
+            "April",
+            "May",
+            "June",
+            "July",
+            "August",
+            "September",
+            "October",
+            "November",
+            "December",
+        ],
+        Language::De => [
+            "Januar",
+            "Februar",
+            "März",
+            "April",
+            "Mai",
+            "Juni",
+            "Juli",
+            "August",
+            "September",
+            "Oktober",
+            "November",
+            "Dezember",
+        ],
+        Language::Es => [
+            "enero",
+            "febrero",
+            "marzo",
+            "abril",
+            "mayo",
+            "junio",
+            "julio",
+            "agosto",
+            "septiembre",
+            "octubre",
+            "noviembre",
+            "diciembre",
+        ],
+        Language::Fr => [
+            "janvier",
+            "février",
+            "mars",
+            "avril",
+            "mai",
+            "juin",
+            "juillet",
+            "août",
+            "septembre",
+            "octobre",
+            "novembre",
+            "décembre",
+        ],
+        Language::Ja | Language::Zh => [
+            "1月", "2月", "3月", "4月", "5月", "6月", "7月", "8月", "9月", "10月", "11月", "12月",
+        ],
+    };
+
+    names[month0 as usize]
+}
+
+fn month_abbr(language: Language, month0: u32) -> &'static str {
+    let names = match language {
+        Language::En => [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ],
+        Language::De => [
+            "Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez",
+        ],
+        Language::Es => [
+            "ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sept", "oct", "nov", "dic",
+        ],
+        Language::Fr => [
+            "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.",
+            "nov.", "déc.",
+        ],
+        Language::Ja | Language::Zh => return month_name(language, month0),
+    };
+
+    names[month0 as usize]
+}
+
+fn weekday_name(language: Language, weekday0: u32) -> &'static str {
+    let names = match language {
+        Language::En => [
+            "Monday",
+            "Tuesday",
+            "Wednesday",
+            "Thursday",
+            "Friday",
+            "Saturday",
+            "Sunday",
+        ],
+        Language::De => [
+            "Montag",
+            "Dienstag",
+            "Mittwoch",
+            "Donnerstag",
+            "Freitag",
+            "Samstag",
+            "Sonntag",
+        ],
+        Language::Es => [
+            "lunes",
+            "martes",
+            "miércoles",
+            "jueves",
+            "viernes",
+            "sábado",
+            "domingo",
+        ],
+        Language::Fr => [
+            "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+        ],
+        Language::Ja => [
+            "月曜日",
+            "火曜日",
+            "水曜日",
+            "木曜日",
+            "金曜日",
+            "土曜日",
+            "日曜日",
+        ],
+        Language::Zh => [
+            "星期一",
+            "星期二",
+            "星期三",
+            "星期四",
+            "星期五",
+            "星期六",
+            "星期日",
+        ],
+    };
+
+    names[weekday0 as usize]
+}
+
+fn weekday_abbr(language: Language, weekday0: u32) -> &'static str {
+    let names = match language {
+        Language::En => ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+        Language::De => ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+        Language::Es => ["lun", "mar", "mié", "jue", "vie", "sáb", "dom"],
+        Language::Fr => ["lun.", "mar.", "mer.", "jeu.", "ven.", "sam.", "dim."],
+        Language::Ja => ["月", "火", "水", "木", "金", "土", "日"],
+        Language::Zh => ["周一", "周二", "周三", "周四", "周五", "周六", "周日"],
+    };
+
+    names[weekday0 as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn time() -> DateTime<Tz> {
+        Tz::UTC.with_ymd_and_hms(2021, 12, 1, 15, 4, 5).unwrap()
+    }
+
+    #[test]
+    fn localized_names() {
+        let format = |pattern, language| {
+            time()
+                .format(&localize_pattern(pattern, &time(), language))
+                .to_string()
+        };
+
+        assert_eq!(format("%A %-d %B", Language::De), "Mittwoch 1 Dezember");
+        assert_eq!(format("%a %b %%B", Language::Fr), "mer. déc. %B");
+        assert_eq!(format("%c", Language::De), "Mi 01 Dez 2021 15:04:05");
+        assert_eq!(format("%x", Language::Es), "01/12/21");
+        assert_eq!(format("%c", Language::Ja), "2021年12月01日 15時04分05秒");
+        assert_eq!(
+            format("%c", Language::Zh),
+            "2021年12月01日 星期三 15时04分05秒"
+        );
+        assert_eq!(format("%r", Language::Ja), "03:04:05 午後");
+        assert_eq!(format("%p", Language::Fr), "PM");
+        assert_eq!(format("%+", Language::Fr), "2021-12-01T15:04:05+00:00");
+    }
+
+    #[test]
+    fn relative_times() {
+        assert_eq!(relative(30, Language::En), "just now");
+        assert_eq!(relative(60, Language::En), "1 minute ago");
+        assert_eq!(relative(-7200, Language::En), "in 2 hours");
+        assert_eq!(relative(86400, Language::De), "vor 1 Tag");
+        assert_eq!(relative(-3 * 86400, Language::De), "in 3 Tagen");
+        assert_eq!(relative(2 * 2592000, Language::Es), "hace 2 meses");
+        assert_eq!(relative(-31536000, Language::Fr), "dans 1 an");
+        assert_eq!(relative(-30, Language::Fr), "à l'instant");
+        assert_eq!(relative(600, Language::Ja), "10分前");
+        assert_eq!(relative(-3600, Language::Zh), "1小时后");
+    }
+}
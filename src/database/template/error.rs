@@ -0,0 +1,110 @@
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TemplateError {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl TemplateError {
+    pub fn new(file: &str, source: &str, offset: usize, message: String) -> Self {
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+
+        Self {
+            file: file.to_owned(),
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            message,
+        }
+    }
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.file, self.line, self.column, self.message
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TemplateErrors(pub Vec<TemplateError>);
+
+impl TemplateErrors {
+    pub fn push(&mut self, error: TemplateError) {
+        if !self.0.contains(&error) {
+            self.0.push(error);
+        }
+    }
+
+    pub fn extend(&mut self, errors: TemplateErrors) {
+        errors.0.into_iter().for_each(|error| self.push(error));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl Display for TemplateErrors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (idx, error) in self.0.iter().enumerate() {
+            if idx != 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", error)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn position() {
+        let source = "ab\n日本語 {:x}\n\n{:y}";
+        let error = |offset| TemplateError::new("a.html", source, offset, "msg".to_owned());
+
+        assert_eq!(error(0).to_string(), "a.html:1:1: msg");
+        assert_eq!(
+            error(source.find("{:x}").unwrap()).to_string(),
+            "a.html:2:5: msg"
+        );
+        assert_eq!(
+            error(source.find("{:y}").unwrap()).to_string(),
+            "a.html:4:1: msg"
+        );
+    }
+
+    #[test]
+    fn collect_errors() {
+        let source = "{:x}\n{:y}";
+        let mut errors = TemplateErrors::default();
+        errors.push(TemplateError::new("a.html", source, 0, "x".to_owned()));
+        errors.push(TemplateError::new("a.html", source, 5, "y".to_owned()));
+        errors.push(TemplateError::new("a.html", source, 0, "x".to_owned()));
+
+        let mut more = TemplateErrors::default();
+        more.push(TemplateError::new("a.html", source, 5, "y".to_owned()));
+        more.push(TemplateError::new("b.html", source, 5, "y".to_owned()));
+        errors.extend(more);
+
+        assert_eq!(errors.len(), 3);
+        assert_eq!(
+            errors.to_string(),
+            "a.html:1:1: x\na.html:2:1: y\nb.html:2:1: y"
+        );
+    }
+}
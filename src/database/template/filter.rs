@@ -0,0 +1,202 @@
+use super::{date::DateFormat, html};
+use anyhow::{bail, Result};
+use std::borrow::Cow;
+
+#[derive(Clone, Debug)]
+pub enum Filter {
+    Date(DateFormat),
+    Raw,
+    Upper,
+    Truncate(usize),
+    UrlEncode,
+    Json,
+    StripHtml,
+    Default(String),
+}
+
+impl Filter {
+    pub fn parse(str: &str) -> Result<Self> {
+        let (name, arg) = match str.split_once(':') {
+            Some((name, arg)) => (name.trim(), Some(arg.trim())),
+            None => (str.trim(), None),
+        };
+
+        match (name, arg) {
+            ("date", Some(format)) => Ok(Self::Date(DateFormat::parse(format)?)),
+            ("raw" | "safe", None) => Ok(Self::Raw),
+            ("upper", None) => Ok(Self::Upper),
+            ("truncate", Some(len)) => match len.parse() {
+                Ok(len) => Ok(Self::Truncate(len)),
+                Err(_) => bail!("The `truncate` filter needs a length, like `truncate:100`"),
+            },
+            ("urlencode", None) => Ok(Self::UrlEncode),
+            ("json", None) => Ok(Self::Json),
+            ("strip_html", None) => Ok(Self::StripHtml),
+            ("default", Some(default)) => Ok(Self::Default(default.to_owned())),
+            ("date", None) => bail!("The `date` filter needs a format, like `date:%Y-%m-%d`"),
+            ("truncate" | "default", None) => bail!("The `{}` filter needs an argument", name),
+            ("raw" | "safe" | "upper" | "urlencode" | "json" | "strip_html", Some(_)) => {
+                bail!("The `{}` filter takes no argument", name)
+            }
+            _ => bail!("Unknown filter: {}", name),
+        }
+    }
+
+    pub fn apply<'a>(&self, value: Cow<'a, str>) -> Cow<'a, str> {
+        match self {
+            Self::Date(_) | Self::Raw => value,
+            Self::Upper => Cow::Owned(value.to_uppercase()),
+            Self::Truncate(len) => match value.char_indices().nth(*len) {
+                Some((idx, _)) => Cow::Owned(format!("{}…", value[..idx].trim_end())),
+                None => value,
+            },
+            Self::UrlEncode => {
+                Cow::Owned(form_urlencoded::byte_serialize(value.as_bytes()).collect())
+            }
+            Self::Json => Cow::Owned(to_json_string(&value)),
+            Self::StripHtml => Cow::Owned(strip_html(&value)),
+            Self::Default(default) if value.is_empty() => Cow::Owned(default.to_owned()),
+            Self::Default(_) => value,
+        }
+    }
+
+    pub fn keeps_html(&self) -> bool {
+        matches!(self, Self::Raw | Self::Default(_))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Escape {
+    None,
+    Html,
+    Script,
+}
+
+impl Escape {
+    pub fn apply<'a>(&self, value: Cow<'a, str>) -> Cow<'a, str> {
+        match self {
+            Self::None => value,
+            Self::Html => Cow::Owned(html::escape(&value)),
+            Self::Script => Cow::Owned(escape_script(&value)),
+        }
+    }
+}
+
+fn to_json_string(str: &str) -> String {
+    format!(r#""{}""#, escape_script(str))
+}
+
+// Escapes a value to be placed inside a JavaScript / JSON string literal, also keeping it from closing the `<script>` element
+fn escape_script(str: &str) -> String {
+    let mut result = String::with_capacity(str.len());
+
+    for c in str.chars() {
+        match c {
+            '"' => result.push_str(r#"\""#),
+            '\'' => result.push_str(r"\u0027"),
+            '\\' => result.push_str(r"\\"),
+            '<' => result.push_str(r"\u003c"),
+            '>' => result.push_str(r"\u003e"),
+            '&' => result.push_str(r"\u0026"),
+            '\n' => result.push_str(r"\n"),
+            '\r' => result.push_str(r"\r"),
+            '\t' => result.push_str(r"\t"),
+            '\u{2028}' => result.push_str(r"\u2028"),
+            '\u{2029}' => result.push_str(r"\u2029"),
+            c if c.is_control() => result.push_str(&format!(r"\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+
+    result
+}
+
+fn strip_html(str: &str) -> String {
+    let mut text = String::with_capacity(str.len());
+    let mut in_tag = false;
+
+    for c in str.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(filter: &str, value: &str) -> String {
+        Filter::parse(filter)
+            .unwrap()
+            .apply(Cow::Borrowed(value))
+            .into_owned()
+    }
+
+    #[test]
+    fn parse_filters() {
+        assert!(matches!(Filter::parse(" safe "), Ok(Filter::Raw)));
+        assert!(matches!(
+            Filter::parse("truncate: 3"),
+            Ok(Filter::Truncate(3))
+        ));
+        assert!(
+            matches!(Filter::parse("default:a:b"), Ok(Filter::Default(default)) if default == "a:b")
+        );
+
+        for filter in [
+            "nope",
+            "truncate",
+            "truncate:-1",
+            "default",
+            "upper:1",
+            "date",
+            "date:%Q",
+        ] {
+            assert!(Filter::parse(filter).is_err(), "{}", filter);
+        }
+    }
+
+    #[test]
+    fn apply_filters() {
+        assert_eq!(apply("truncate:5", "héllo world"), "héllo…");
+        assert_eq!(apply("truncate:6", "héllo world"), "héllo…");
+        assert_eq!(apply("truncate:20", "héllo world"), "héllo world");
+        assert_eq!(apply("upper", "straße"), "STRASSE");
+        assert_eq!(apply("urlencode", "a b&c/é"), "a+b%26c%2F%C3%A9");
+        assert_eq!(apply("default:none", ""), "none");
+        assert_eq!(apply("default:none", "some"), "some");
+        assert_eq!(
+            apply("strip_html", "<p>a &lt;b&gt; &amp;amp;</p><br/>c"),
+            "a <b> &amp;c"
+        );
+        assert_eq!(
+            apply("json", "\"'<\\>\u{2028}\u{1}"),
+            r#""\"\u0027\u003c\\\u003e\u2028\u0001""#
+        );
+    }
+
+    #[test]
+    fn escape_values() {
+        let value = "</script><a href=\"x\">'&'";
+
+        assert_eq!(Escape::None.apply(Cow::Borrowed(value)), value);
+        assert_eq!(
+            Escape::Html.apply(Cow::Borrowed(value)),
+            "&lt;/script&gt;&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;"
+        );
+        assert_eq!(
+            Escape::Script.apply(Cow::Borrowed(value)),
+            r#"\u003c/script\u003e\u003ca href=\"x\"\u003e\u0027\u0026\u0027"#
+        );
+    }
+}
@@ -0,0 +1,367 @@
+use super::{
+    html,
+    markdown::{self, Renderer},
+};
+use crate::{config::ContentFormat, Config};
+use anyhow::Result;
+use once_cell::sync::OnceCell;
+use regex::{Captures, Regex};
+use std::path::Path;
+
+static ASCIIDOC_INLINE: OnceCell<[Regex; 4]> = OnceCell::new();
+
+pub trait Format: Sync {
+    fn extensions(&self) -> &'static [&'static str];
+
+    fn render(&self, renderer: &Renderer<'_>, source: &str) -> Result<(String, String)>;
+
+    fn to_text(&self, source: &str) -> String;
+}
+
+pub fn from_path(path: &Path) -> Option<&'static dyn Format> {
+    let ext = path.extension()?.to_str()?;
+
+    Config::read()
+        .content
+        .formats
+        .iter()
+        .map(|format| -> &'static dyn Format {
+            match format {
+                ContentFormat::Markdown => &Markdown,
+                ContentFormat::Html => &Html,
+                ContentFormat::AsciiDoc => &AsciiDoc,
+                ContentFormat::PlainText => &PlainText,
+            }
+        })
+        .find(|format| format.extensions().contains(&ext))
+}
+
+struct Markdown;
+
+impl Format for Markdown {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["md"]
+    }
+
+    fn render(&self, renderer: &Renderer<'_>, source: &str) -> Result<(String, String)> {
+        renderer.md_to_html_with_toc(source)
+    }
+
+    fn to_text(&self, source: &str) -> String {
+        markdown::md_to_text(source)
+    }
+}
+
+struct Html;
+
+impl Format for Html {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["html", "htm"]
+    }
+
+    fn render(&self, _: &Renderer<'_>, source: &str) -> Result<(String, String)> {
+        Ok((source.to_owned(), String::new()))
+    }
+
+    fn to_text(&self, source: &str) -> String {
+        markdown::html_to_text(source)
+    }
+}
+
+struct PlainText;
+
+impl Format for PlainText {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["txt"]
+    }
+
+    fn render(&self, _: &Renderer<'_>, source: &str) -> Result<(String, String)> {
+        let mut result = String::new();
+
+        for paragraph in source
+            .replace(markdown::MORE, "")
+            .split("\n\n")
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+        {
+            result.push_str("<p>");
+            html::push_escaped(&mut result, paragraph);
+            result.push_str("</p>\n");
+        }
+
+        Ok((result, String::new()))
+    }
+
+    fn to_text(&self, source: &str) -> String {
+        source.replace(markdown::MORE, "")
+    }
+}
+
+// The common subset of AsciiDoc, rendered by converting it to Markdown
+struct AsciiDoc;
+
+impl Format for AsciiDoc {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["adoc", "asciidoc"]
+    }
+
+    fn render(&self, renderer: &Renderer<'_>, source: &str) -> Result<(String, String)> {
+        renderer.md_to_html_with_toc(&asciidoc_to_md(source))
+    }
+
+    fn to_text(&self, source: &str) -> String {
+        markdown::md_to_text(&asciidoc_to_md(source))
+    }
+}
+
+fn asciidoc_to_md(source: &str) -> String {
+    let mut result = String::with_capacity(source.len());
+    let mut lines = source.lines();
+    let mut lang = "";
+    let mut id = None;
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_end();
+
+        match trimmed {
+            "----" | "...." | "```" => {
+                result.push_str("```");
+                result.push_str(lang);
+                result.push('\n');
+                for line in lines.by_ref().take_while(|line| line.trim_end() != trimmed) {
+                    result.push_str(line);
+                    result.push('\n');
+                }
+                result.push_str("```\n");
+                lang = "";
+                continue;
+            }
+            "////" => {
+                lines.by_ref().find(|line| line.trim_end() == "////");
+                continue;
+            }
+            "____" => {
+                for line in lines.by_ref().take_while(|line| line.trim_end() != trimmed) {
+                    result.push_str("> ");
+                    result.push_str(&asciidoc_inline(line));
+                    result.push('\n');
+                }
+                result.push('\n');
+                continue;
+            }
+            "'''" => {
+                result.push_str("---\n");
+                continue;
+            }
+            "--" | "====" | "****" | "<<<" => continue,
+            _ => {}
+        }
+
+        if let Some(attrs) = trimmed
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            if let Some(anchor) = attrs
+                .strip_prefix('[')
+                .and_then(|anchor| anchor.strip_suffix(']'))
+                .or_else(|| attrs.strip_prefix('#'))
+            {
+                id = Some(anchor.to_owned());
+            } else if let Some(source_lang) = attrs.strip_prefix("source,") {
+                lang = source_lang.split(',').next().unwrap().trim();
+            }
+            continue;
+        }
+
+        let is_attribute = trimmed
+            .strip_prefix(':')
+            .and_then(|line| line.split_once(':'))
+            .is_some_and(|(name, value)| {
+                !name.is_empty()
+                    && !name.contains(' ')
+                    && (value.is_empty() || value.starts_with(' '))
+            });
+        if is_attribute || (trimmed.starts_with("//") && !trimmed.starts_with("///")) {
+            continue;
+        }
+
+        let level = trimmed.chars().take_while(|c| *c == '=').count();
+        if level > 0 && trimmed[level..].starts_with(' ') {
+            result.push_str(&"#".repeat(level.min(6)));
+            result.push_str(&asciidoc_inline(&trimmed[level..]));
+            if let Some(id) = id.take() {
+                result.push_str(" {#");
+                result.push_str(&id);
+                result.push('}');
+            }
+            result.push('\n');
+            continue;
+        }
+
+        if let Some(image) = trimmed.strip_prefix("image::") {
+            result.push_str(&asciidoc_inline(&format!("image:{}", image)));
+            result.push('\n');
+            continue;
+        }
+
+        let admonition = [
+            ("NOTE: ", "note", ""),
+            ("TIP: ", "tip", ""),
+            ("WARNING: ", "warning", ""),
+            ("IMPORTANT: ", "warning", " \"Important\""),
+            ("CAUTION: ", "warning", " \"Caution\""),
+        ]
+        .into_iter()
+        .find_map(|(prefix, name, title)| {
+            trimmed.strip_prefix(prefix).map(|text| (name, title, text))
+        });
+        if let Some((name, title, text)) = admonition {
+            result.push_str(&format!("{{{{< {}{} >}}}}\n", name, title));
+            result.push_str(&asciidoc_inline(text));
+            result.push('\n');
+            for line in lines.by_ref().take_while(|line| !line.trim().is_empty()) {
+                result.push_str(&asciidoc_inline(line.trim_end()));
+                result.push('\n');
+            }
+            result.push_str(&format!("{{{{< /{} >}}}}\n\n", name));
+            continue;
+        }
+
+        let bullets = trimmed
+            .chars()
+            .take_while(|c| *c == '*' || *c == '-')
+            .count();
+        let numbers = trimmed.chars().take_while(|c| *c == '.').count();
+        if bullets > 0 && trimmed[bullets..].starts_with(' ') {
+            result.push_str(&"  ".repeat(bullets - 1));
+            result.push('-');
+            result.push_str(&asciidoc_inline(&trimmed[bullets..]));
+        } else if numbers > 0 && trimmed[numbers..].starts_with(' ') {
+            result.push_str(&"   ".repeat(numbers - 1));
+            result.push_str("1.");
+            result.push_str(&asciidoc_inline(&trimmed[numbers..]));
+        } else if numbers == 1 && trimmed.len() > 1 {
+            result.push_str("**");
+            result.push_str(&asciidoc_inline(&trimmed[1..]));
+            result.push_str("**\n");
+        } else {
+            result.push_str(&asciidoc_inline(trimmed));
+        }
+        result.push('\n');
+    }
+
+    result
+}
+
+fn asciidoc_inline(text: &str) -> String {
+    let [image, link, xref, bold] = ASCIIDOC_INLINE.get_or_init(|| {
+        [
+            Regex::new(r"image:([^\s\[:][^\s\[]*)\[([^\]]*)\]").unwrap(),
+            Regex::new(r"(?:link:|\b(https?://))([^\s\[]+)\[([^\]]*)\]").unwrap(),
+            Regex::new(r"<<([^,>]+)(?:,\s*([^>]+))?>>").unwrap(),
+            Regex::new(r"(^|[^\w*])\*([^\s*](?:[^*]*[^\s*])?)\*($|[^\w*])").unwrap(),
+        ]
+    });
+
+    let text = image.replace_all(text, "![$2]($1)");
+    let text = link.replace_all(&text, |cap: &Captures| {
+        let url = format!("{}{}", cap.get(1).map_or("", |m| m.as_str()), &cap[2]);
+        match &cap[3] {
+            "" => format!("<{}>", url),
+            title => format!("[{}]({})", title, url),
+        }
+    });
+    let text = xref.replace_all(&text, |cap: &Captures| {
+        let id = cap[1].trim();
+        format!("[{}](#{})", cap.get(2).map_or(id, |m| m.as_str()), id)
+    });
+    let text = bold.replace_all(&text, "$1**$2**$3");
+
+    text.into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::template::tests::with_renderer;
+
+    #[test]
+    fn plain_text() {
+        let source = "a < b\n\n<!--more-->\n\nc\nd<!--more-->\n\n\n";
+        let (html, _) = with_renderer(|renderer| PlainText.render(&renderer, source).unwrap());
+
+        assert_eq!(html.0, "<p>a &lt; b</p>\n<p>c\nd</p>\n");
+        assert!(!PlainText.to_text(source).contains("more"));
+    }
+
+    #[test]
+    fn asciidoc_headings() {
+        assert_eq!(
+            asciidoc_to_md("= Title\n:toc:\n:author: Me\n// comment\n[[intro]]\n== Intro\n[#setup]\n=== Set *up*\n"),
+            "# Title\n## Intro {#intro}\n### Set **up** {#setup}\n"
+        );
+        assert_eq!(asciidoc_to_md("=No space\n"), "=No space\n");
+    }
+
+    #[test]
+    fn asciidoc_blocks() {
+        assert_eq!(
+            asciidoc_to_md("[source,rust]\n----\nlet a = *b*;\n----\n....\nliteral\n....\n"),
+            "```rust\nlet a = *b*;\n```\n```\nliteral\n```\n"
+        );
+        assert_eq!(
+            asciidoc_to_md("////\nhidden\n////\n____\nquoted *x*\n____\n'''\n"),
+            "> quoted **x**\n\n---\n"
+        );
+    }
+
+    #[test]
+    fn asciidoc_admonitions() {
+        assert_eq!(
+            asciidoc_to_md("NOTE: Take care\nof this.\n\nIMPORTANT: Really.\n"),
+            "{{< note >}}\nTake care\nof this.\n{{< /note >}}\n\n{{< warning \"Important\" >}}\nReally.\n{{< /warning >}}\n\n"
+        );
+    }
+
+    #[test]
+    fn asciidoc_lists() {
+        assert_eq!(
+            asciidoc_to_md("* one\n** two\n- three\n. first\n.. second\n.Block title\n"),
+            "- one\n  - two\n- three\n1. first\n   1. second\n**Block title**\n\n"
+        );
+    }
+
+    #[test]
+    fn asciidoc_inline_markup() {
+        assert_eq!(
+            asciidoc_inline(
+                "link:/about[About], https://example.com[Example] and https://example.com[]"
+            ),
+            "[About](/about), [Example](https://example.com) and <https://example.com>"
+        );
+        assert_eq!(
+            asciidoc_inline("<<intro>>, <<intro, the intro>> and image:/a.png[Alt]"),
+            "[intro](#intro), [the intro](#intro) and ![Alt](/a.png)"
+        );
+        assert_eq!(
+            asciidoc_inline("*bold* and 2*3*4 stay"),
+            "**bold** and 2*3*4 stay"
+        );
+    }
+
+    #[test]
+    fn asciidoc_render() {
+        let source = "== Hi\n\nTIP: Use link:/x[x].\n";
+        let ((html, _), problems) =
+            with_renderer(|renderer| AsciiDoc.render(&renderer, source).unwrap());
+
+        assert!(html.contains("Hi</h2>"), "{}", html);
+        assert!(
+            html.contains(r#"<div class="callout callout-tip"><p class="callout-title">Tip</p>"#),
+            "{}",
+            html
+        );
+        assert!(html.contains(r#"<a href="/x">x</a>"#), "{}", html);
+        assert!(problems.is_empty());
+    }
+}
@@ -0,0 +1,780 @@
+use super::html;
+use crate::{config::HighlightTheme, Config};
+use once_cell::sync::OnceCell;
+
+static CSS: OnceCell<String> = OnceCell::new();
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TokenKind {
+    Keyword,
+    Type,
+    Literal,
+    Function,
+    String,
+    Number,
+    Comment,
+}
+
+impl TokenKind {
+    const ALL: [Self; 7] = [
+        Self::Keyword,
+        Self::Type,
+        Self::Literal,
+        Self::Function,
+        Self::String,
+        Self::Number,
+        Self::Comment,
+    ];
+
+    fn class(&self) -> &'static str {
+        match self {
+            Self::Keyword => "hl-keyword",
+            Self::Type => "hl-type",
+            Self::Literal => "hl-literal",
+            Self::Function => "hl-function",
+            Self::String => "hl-string",
+            Self::Number => "hl-number",
+            Self::Comment => "hl-comment",
+        }
+    }
+}
+
+struct Syntax {
+    keywords: &'static [&'static str],
+    types: &'static [&'static str],
+    literals: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [&'static str],
+    capitalized_types: bool,
+}
+
+const C_KEYWORDS: &[&str] = &[
+    "auto",
+    "break",
+    "case",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "else",
+    "enum",
+    "extern",
+    "for",
+    "goto",
+    "if",
+    "inline",
+    "register",
+    "return",
+    "sizeof",
+    "static",
+    "struct",
+    "switch",
+    "typedef",
+    "union",
+    "volatile",
+    "while",
+    "#include",
+    "#define",
+    "#ifdef",
+    "#ifndef",
+    "#endif",
+    "#if",
+    "#else",
+    "class",
+    "namespace",
+    "new",
+    "delete",
+    "private",
+    "protected",
+    "public",
+    "template",
+    "typename",
+    "this",
+    "throw",
+    "try",
+    "catch",
+    "using",
+    "virtual",
+    "override",
+];
+const C_TYPES: &[&str] = &[
+    "char", "double", "float", "int", "long", "short", "signed", "unsigned", "void", "bool",
+    "size_t", "auto",
+];
+
+const SYNTAXES: &[(&[&str], Syntax)] = &[
+    (
+        &["rust", "rs"],
+        Syntax {
+            keywords: &[
+                "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else",
+                "enum", "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+                "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+                "trait", "type", "unsafe", "use", "where", "while",
+            ],
+            types: &[
+                "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16",
+                "i32", "i64", "i128", "isize", "f32", "f64",
+            ],
+            literals: &["true", "false", "None", "Some", "Ok", "Err"],
+            line_comments: &["//"],
+            block_comment: Some(("/*", "*/")),
+            quotes: &["\""],
+            capitalized_types: true,
+        },
+    ),
+    (
+        &["c", "h", "cpp", "c++", "cc", "hpp"],
+        Syntax {
+            keywords: C_KEYWORDS,
+            types: C_TYPES,
+            literals: &["true", "false", "NULL", "nullptr"],
+            line_comments: &["//"],
+            block_comment: Some(("/*", "*/")),
+            quotes: &["\"", "'"],
+            capitalized_types: false,
+        },
+    ),
+    (
+        &["go", "golang"],
+        Syntax {
+            keywords: &[
+                "break",
+                "case",
+                "chan",
+                "const",
+                "continue",
+                "default",
+                "defer",
+                "else",
+                "fallthrough",
+                "for",
+                "func",
+                "go",
+                "goto",
+                "if",
+                "import",
+                "interface",
+                "map",
+                "package",
+                "range",
+                "return",
+                "select",
+                "struct",
+                "switch",
+                "type",
+                "var",
+            ],
+            types: &[
+                "bool", "byte", "error", "float32", "float64", "int", "int8", "int16", "int32",
+                "int64", "rune", "string", "uint", "uint8", "uint16", "uint32", "uint64",
+                "uintptr",
+            ],
+            literals: &["true", "false", "nil", "iota"],
+            line_comments: &["//"],
+            block_comment: Some(("/*", "*/")),
+            quotes: &["\"", "'", "`"],
+            capitalized_types: false,
+        },
+    ),
+    (
+        &["java", "kotlin", "kt", "csharp", "cs", "c#"],
+        Syntax {
+            keywords: &[
+                "abstract",
+                "break",
+                "case",
+                "catch",
+                "class",
+                "continue",
+                "default",
+                "do",
+                "else",
+                "enum",
+                "extends",
+                "final",
+                "finally",
+                "for",
+                "fun",
+                "if",
+                "implements",
+                "import",
+                "instanceof",
+                "interface",
+                "namespace",
+                "new",
+                "override",
+                "package",
+                "private",
+                "protected",
+                "public",
+                "return",
+                "static",
+                "super",
+                "switch",
+                "this",
+                "throw",
+                "throws",
+                "try",
+                "using",
+                "val",
+                "var",
+                "void",
+                "when",
+                "while",
+            ],
+            types: &[
+                "boolean", "bool", "byte", "char", "double", "float", "int", "long", "short",
+                "string",
+            ],
+            literals: &["true", "false", "null"],
+            line_comments: &["//"],
+            block_comment: Some(("/*", "*/")),
+            quotes: &["\"", "'"],
+            capitalized_types: true,
+        },
+    ),
+    (
+        &["javascript", "js", "jsx", "mjs", "typescript", "ts", "tsx"],
+        Syntax {
+            keywords: &[
+                "async",
+                "await",
+                "break",
+                "case",
+                "catch",
+                "class",
+                "const",
+                "continue",
+                "debugger",
+                "default",
+                "delete",
+                "do",
+                "else",
+                "export",
+                "extends",
+                "finally",
+                "for",
+                "from",
+                "function",
+                "if",
+                "import",
+                "in",
+                "instanceof",
+                "interface",
+                "let",
+                "new",
+                "of",
+                "return",
+                "static",
+                "super",
+                "switch",
+                "this",
+                "throw",
+                "try",
+                "type",
+                "typeof",
+                "var",
+                "void",
+                "while",
+                "yield",
+            ],
+            types: &[
+                "any", "boolean", "never", "number", "object", "string", "symbol", "unknown",
+            ],
+            literals: &["true", "false", "null", "undefined", "NaN", "Infinity"],
+            line_comments: &["//"],
+            block_comment: Some(("/*", "*/")),
+            quotes: &["\"", "'", "`"],
+            capitalized_types: true,
+        },
+    ),
+    (
+        &["python", "py"],
+        Syntax {
+            keywords: &[
+                "and", "as", "assert", "async", "await", "break", "class", "continue", "def",
+                "del", "elif", "else", "except", "finally", "for", "from", "global", "if",
+                "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return",
+                "try", "while", "with", "yield", "self",
+            ],
+            types: &[
+                "bool", "bytes", "dict", "float", "int", "list", "object", "set", "str", "tuple",
+            ],
+            literals: &["True", "False", "None"],
+            line_comments: &["#"],
+            block_comment: None,
+            quotes: &["\"\"\"", "'''", "\"", "'"],
+            capitalized_types: true,
+        },
+    ),
+    (
+        &["bash", "sh", "shell", "zsh", "console"],
+        Syntax {
+            keywords: &[
+                "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function",
+                "if", "in", "local", "return", "then", "until", "while",
+            ],
+            types: &[],
+            literals: &["true", "false"],
+            line_comments: &["#"],
+            block_comment: None,
+            quotes: &["\"", "'"],
+            capitalized_types: false,
+        },
+    ),
+    (
+        &["toml", "yaml", "yml", "ini"],
+        Syntax {
+            keywords: &[],
+            types: &[],
+            literals: &["true", "false", "null", "yes", "no"],
+            line_comments: &["#", ";"],
+            block_comment: None,
+            quotes: &["\"\"\"", "'''", "\"", "'"],
+            capitalized_types: false,
+        },
+    ),
+    (
+        &["json"],
+        Syntax {
+            keywords: &[],
+            types: &[],
+            literals: &["true", "false", "null"],
+            line_comments: &[],
+            block_comment: None,
+            quotes: &["\""],
+            capitalized_types: false,
+        },
+    ),
+];
+
+pub fn highlight(info: &str, code: &str) -> String {
+    let mut info = info.split_whitespace();
+    let lang = info.next().unwrap_or("");
+
+    let mut line_numbers = false;
+    let mut highlighted_lines = Vec::new();
+
+    for option in info {
+        if option == "linenos" {
+            line_numbers = true;
+        } else if let Some(ranges) = option.strip_prefix("hl_lines=") {
+            highlighted_lines.extend(ranges.split(',').filter_map(parse_range));
+        }
+    }
+
+    let tokens = match SYNTAXES
+        .iter()
+        .find(|(names, _)| names.contains(&lang.to_ascii_lowercase().as_str()))
+    {
+        Some((_, syntax)) => tokenize(syntax, code),
+        None => vec![(None, code)],
+    };
+
+    let inline_styles = Config::read().highlight.inline_styles;
+    let colors = Colors::from_theme(Config::read().highlight.theme);
+    let wrap_lines = line_numbers || !highlighted_lines.is_empty();
+
+    let mut result = String::new();
+
+    if inline_styles {
+        result.push_str(&format!(
+            r#"<pre class="highlight" style="{}">"#,
+            colors.pre_style()
+        ));
+    } else {
+        result.push_str(r#"<pre class="highlight">"#);
+    }
+
+    if lang.is_empty() {
+        result.push_str("<code>");
+    } else {
+        result.push_str(r#"<code class="language-"#);
+        html::push_escaped(&mut result, lang);
+        result.push_str(r#"">"#);
+    }
+
+    let mut line_num = 1;
+    let open_line = |result: &mut String, line_num: usize| {
+        if !wrap_lines {
+            return;
+        }
+
+        let is_highlighted = highlighted_lines
+            .iter()
+            .any(|(start, end)| (*start..=*end).contains(&line_num));
+
+        match (inline_styles, is_highlighted) {
+            (true, true) => result.push_str(&format!(
+                r#"<span class="line hl-line" style="display:block;background:{}">"#,
+                colors.highlighted_line
+            )),
+            (true, false) => result.push_str(r#"<span class="line" style="display:block">"#),
+            (false, true) => result.push_str(r#"<span class="line hl-line">"#),
+            (false, false) => result.push_str(r#"<span class="line">"#),
+        }
+
+        if line_numbers {
+            if inline_styles {
+                result.push_str(&format!(
+                    r#"<span class="line-number" style="{}">{}</span>"#,
+                    colors.line_number_style(),
+                    line_num
+                ));
+            } else {
+                result.push_str(&format!(r#"<span class="line-number">{}</span>"#, line_num));
+            }
+        }
+    };
+
+    open_line(&mut result, line_num);
+
+    let line_count = code.trim_end_matches('\n').matches('\n').count() + 1;
+
+    for (kind, text) in tokens {
+        let mut lines = text.split('\n').peekable();
+
+        while let Some(line) = lines.next() {
+            if !line.is_empty() {
+                match kind {
+                    Some(kind) if inline_styles => {
+                        result.push_str(&format!(r#"<span style="{}">"#, colors.style(kind)))
+                    }
+                    Some(kind) => result.push_str(&format!(r#"<span class="{}">"#, kind.class())),
+                    None => {}
+                }
+                html::push_escaped(&mut result, line);
+                if kind.is_some() {
+                    result.push_str("</span>");
+                }
+            }
+
+            if lines.peek().is_some() {
+                result.push('\n');
+
+                if wrap_lines {
+                    if line_num <= line_count {
+                        result.push_str("</span>");
+                    }
+                    line_num += 1;
+                    if line_num <= line_count {
+                        open_line(&mut result, line_num);
+                    }
+                }
+            }
+        }
+    }
+
+    if wrap_lines && line_num <= line_count {
+        result.push_str("</span>");
+    }
+
+    result.push_str("</code></pre>\n");
+
+    result
+}
+
+pub fn css() -> &'static str {
+    CSS.get_or_init(|| {
+        let colors = Colors::from_theme(Config::read().highlight.theme);
+
+        let mut css = format!(".highlight{{{}}}", colors.pre_style());
+        css.push_str(".highlight .line{display:block}");
+        css.push_str(&format!(
+            ".highlight .hl-line{{background:{}}}",
+            colors.highlighted_line
+        ));
+        css.push_str(&format!(
+            ".highlight .line-number{{{}}}",
+            colors.line_number_style()
+        ));
+
+        for kind in TokenKind::ALL {
+            css.push_str(&format!(
+                ".highlight .{}{{{}}}",
+                kind.class(),
+                colors.style(kind)
+            ));
+        }
+
+        css
+    })
+}
+
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once('-') {
+        Some((start, end)) => Some((start.trim().parse().ok()?, end.trim().parse().ok()?)),
+        None => {
+            let line = range.trim().parse().ok()?;
+            Some((line, line))
+        }
+    }
+}
+
+fn tokenize<'c>(syntax: &Syntax, code: &'c str) -> Vec<(Option<TokenKind>, &'c str)> {
+    let mut tokens = Vec::new();
+    let mut plain_start = 0;
+    let mut idx = 0;
+
+    while idx < code.len() {
+        let rest = &code[idx..];
+        let c = rest.chars().next().unwrap();
+
+        let token = if let Some(comment) = syntax
+            .line_comments
+            .iter()
+            .find(|comment| rest.starts_with(*comment))
+        {
+            let len = rest.find('\n').unwrap_or(rest.len()).max(comment.len());
+            Some((TokenKind::Comment, len))
+        } else if let Some((start, end)) = syntax
+            .block_comment
+            .filter(|(start, _)| rest.starts_with(start))
+        {
+            let len = rest[start.len()..]
+                .find(end)
+                .map_or(rest.len(), |pos| start.len() + pos + end.len());
+            Some((TokenKind::Comment, len))
+        } else if let Some(quote) = syntax.quotes.iter().find(|quote| rest.starts_with(*quote)) {
+            Some((TokenKind::String, string_len(rest, quote)))
+        } else if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+                .unwrap_or(rest.len());
+            Some((TokenKind::Number, len))
+        } else if c.is_alphabetic() || c == '_' || (c == '#' && !syntax.keywords.is_empty()) {
+            let len = rest[c.len_utf8()..]
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .map_or(rest.len(), |pos| pos + c.len_utf8());
+            let word = &rest[..len];
+            let is_call = rest[len..].trim_start().starts_with('(');
+
+            let kind = if syntax.keywords.contains(&word) {
+                Some(TokenKind::Keyword)
+            } else if syntax.literals.contains(&word) {
+                Some(TokenKind::Literal)
+            } else if syntax.types.contains(&word)
+                || (syntax.capitalized_types && word.starts_with(char::is_uppercase))
+            {
+                Some(TokenKind::Type)
+            } else if is_call && !syntax.keywords.is_empty() {
+                Some(TokenKind::Function)
+            } else {
+                None
+            };
+
+            match kind {
+                Some(kind) => Some((kind, len)),
+                None => {
+                    idx += len;
+                    continue;
+                }
+            }
+        } else {
+            None
+        };
+
+        match token {
+            Some((kind, len)) => {
+                if plain_start < idx {
+                    tokens.push((None, &code[plain_start..idx]));
+                }
+                tokens.push((Some(kind), &code[idx..idx + len]));
+                idx += len;
+                plain_start = idx;
+            }
+            None => idx += c.len_utf8(),
+        }
+    }
+
+    if plain_start < code.len() {
+        tokens.push((None, &code[plain_start..]));
+    }
+
+    tokens
+}
+
+fn string_len(rest: &str, quote: &str) -> usize {
+    let mut chars = rest[quote.len()..].char_indices();
+
+    while let Some((pos, c)) = chars.next() {
+        if c == '\\' && quote.len() == 1 {
+            chars.next();
+        } else if rest[quote.len() + pos..].starts_with(quote) {
+            return quote.len() + pos + quote.len();
+        } else if c == '\n' && quote.len() == 1 && quote != "`" {
+            return quote.len() + pos;
+        }
+    }
+
+    rest.len()
+}
+
+struct Colors {
+    background: &'static str,
+    text: &'static str,
+    line_number: &'static str,
+    highlighted_line: &'static str,
+    keyword: &'static str,
+    r#type: &'static str,
+    literal: &'static str,
+    function: &'static str,
+    string: &'static str,
+    number: &'static str,
+    comment: &'static str,
+}
+
+impl Colors {
+    fn from_theme(theme: HighlightTheme) -> Self {
+        match theme {
+            HighlightTheme::Light => Self {
+                background: "#fafafa",
+                text: "#383a42",
+                line_number: "#9d9d9f",
+                highlighted_line: "#eaeaeb",
+                keyword: "#a626a4",
+                r#type: "#c18401",
+                literal: "#986801",
+                function: "#4078f2",
+                string: "#50a14f",
+                number: "#986801",
+                comment: "#a0a1a7",
+            },
+            HighlightTheme::Dark => Self {
+                background: "#282c34",
+                text: "#abb2bf",
+                line_number: "#636d83",
+                highlighted_line: "#3e4451",
+                keyword: "#c678dd",
+                r#type: "#e5c07b",
+                literal: "#d19a66",
+                function: "#61afef",
+                string: "#98c379",
+                number: "#d19a66",
+                comment: "#5c6370",
+            },
+        }
+    }
+
+    fn pre_style(&self) -> String {
+        format!(
+            "background:{};color:{};padding:1em;overflow-x:auto",
+            self.background, self.text
+        )
+    }
+
+    fn line_number_style(&self) -> String {
+        format!(
+            "display:inline-block;min-width:2em;margin-right:1em;text-align:right;user-select:none;color:{}",
+            self.line_number
+        )
+    }
+
+    fn style(&self, kind: TokenKind) -> String {
+        match kind {
+            TokenKind::Keyword => format!("color:{}", self.keyword),
+            TokenKind::Type => format!("color:{}", self.r#type),
+            TokenKind::Literal => format!("color:{}", self.literal),
+            TokenKind::Function => format!("color:{}", self.function),
+            TokenKind::String => format!("color:{}", self.string),
+            TokenKind::Number => format!("color:{}", self.number),
+            TokenKind::Comment => format!("color:{};font-style:italic", self.comment),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rust() -> &'static Syntax {
+        &SYNTAXES[0].1
+    }
+
+    #[test]
+    fn tokenize_rust() {
+        let tokens = tokenize(rust(), "let x: u8 = foo(1); // done");
+
+        assert_eq!(
+            tokens,
+            vec![
+                (Some(TokenKind::Keyword), "let"),
+                (None, " x: "),
+                (Some(TokenKind::Type), "u8"),
+                (None, " = "),
+                (Some(TokenKind::Function), "foo"),
+                (None, "("),
+                (Some(TokenKind::Number), "1"),
+                (None, "); "),
+                (Some(TokenKind::Comment), "// done"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_strings_and_comments() {
+        let tokens = tokenize(rust(), r#""a \" b" /* c */ "#);
+
+        assert_eq!(
+            tokens,
+            vec![
+                (Some(TokenKind::String), r#""a \" b""#),
+                (None, " "),
+                (Some(TokenKind::Comment), "/* c */"),
+                (None, " "),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_unterminated() {
+        assert_eq!(
+            tokenize(rust(), "\"open\nnext"),
+            vec![(Some(TokenKind::String), "\"open"), (None, "\nnext")]
+        );
+        assert_eq!(
+            tokenize(rust(), "/* open"),
+            vec![(Some(TokenKind::Comment), "/* open")]
+        );
+    }
+
+    #[test]
+    fn parse_ranges() {
+        assert_eq!(parse_range("3"), Some((3, 3)));
+        assert_eq!(parse_range("2-4"), Some((2, 4)));
+        assert_eq!(parse_range("a-4"), None);
+        assert_eq!(parse_range(""), None);
+    }
+
+    #[test]
+    fn wrapped_lines_are_balanced() {
+        Config::init_for_test();
+
+        for code in ["a\n", "a\n\n", "a\nb", "a\n\nb\n\n\n", "", "\n"] {
+            for info in ["rust linenos", "rust hl_lines=1-2", "text linenos"] {
+                let html = highlight(info, code);
+                assert_eq!(
+                    html.matches("<span").count(),
+                    html.matches("</span>").count(),
+                    "{:?} {:?}: {}",
+                    info,
+                    code,
+                    html
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn trailing_blank_line() {
+        Config::init_for_test();
+
+        assert_eq!(
+            highlight("text linenos", "a\n\n"),
+            "<pre class=\"highlight\"><code class=\"language-text\"><span class=\"line\">\
+             <span class=\"line-number\">1</span>a\n</span>\n</code></pre>\n"
+        );
+    }
+}
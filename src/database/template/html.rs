@@ -0,0 +1,18 @@
+pub fn push_escaped(result: &mut String, str: &str) {
+    for c in str.chars() {
+        match c {
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '&' => result.push_str("&amp;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            c => result.push(c),
+        }
+    }
+}
+
+pub fn escape(str: &str) -> String {
+    let mut result = String::with_capacity(str.len());
+    push_escaped(&mut result, str);
+    result
+}
@@ -1,10 +1,1241 @@
-use pulldown_cmark::{html, Options, Parser};
+use super::{
+    format::{self, Format},
+    highlight, html as html_escape, math,
+};
+use crate::{
+    config::SummaryMode,
+    database::{Images, Problem, ProblemKind},
+    Config,
+};
+use anyhow::{anyhow, bail, Error, Result};
+use once_cell::sync::OnceCell;
+use pulldown_cmark::{html, CodeBlockKind, CowStr, Event, Options, Parser, Tag};
+use regex::{Captures, Regex};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    iter::Peekable,
+    ops::Range,
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+};
 
-pub fn md_to_html(md: &str) -> String {
+const PLACEHOLDER: char = '\u{fffc}';
+pub const MORE: &str = "<!--more-->";
+
+static SHORTCODE_PARAM: OnceCell<Regex> = OnceCell::new();
+
+struct Heading {
+    level: u32,
+    id: String,
+    title: String,
+}
+
+struct Shortcode<'s> {
+    name: &'s str,
+    args: HashMap<String, String>,
+    body: Option<&'s str>,
+    is_block: bool,
+}
+
+#[derive(Clone, Copy)]
+pub struct Renderer<'r> {
+    root: &'r Path,
+    shortcodes: &'r HashMap<String, String>,
+    images: &'r Images,
+    links: &'r HashMap<PathBuf, String>,
+    problems: &'r Mutex<Vec<Problem>>,
+    path: &'r Path,
+}
+
+impl<'r> Renderer<'r> {
+    pub fn new(
+        root: &'r Path,
+        shortcodes: &'r HashMap<String, String>,
+        images: &'r Images,
+        links: &'r HashMap<PathBuf, String>,
+        problems: &'r Mutex<Vec<Problem>>,
+    ) -> Self {
+        Self {
+            root,
+            shortcodes,
+            images,
+            links,
+            problems,
+            path: Path::new(""),
+        }
+    }
+
+    pub fn with_path<'p>(&self, path: &'p Path) -> Renderer<'p>
+    where
+        'r: 'p,
+    {
+        Renderer { path, ..*self }
+    }
+
+    pub fn md_to_html(&self, md: &str) -> Result<String> {
+        Ok(self.render(md)?.0)
+    }
+
+    pub fn md_to_html_with_toc(&self, md: &str) -> Result<(String, String)> {
+        let (html, headings) = self.render(md)?;
+        Ok((html, gen_toc(&headings)))
+    }
+
+    pub fn summary(
+        &self,
+        format: &dyn Format,
+        source: &str,
+        front_matter: Option<&str>,
+        html: &str,
+    ) -> Result<(String, bool)> {
+        if let Some(idx) = source.find(MORE) {
+            let has_more = !source[idx + MORE.len()..].trim().is_empty();
+            return Ok((format.render(self, source[..idx].trim_end())?.0, has_more));
+        }
+
+        if let Some(summary) = front_matter {
+            return Ok((self.md_to_html(summary)?, true));
+        }
+
+        let site = &Config::read().site;
+
+        Ok(match site.summary {
+            SummaryMode::Full => (html.to_owned(), false),
+            SummaryMode::FirstParagraph => first_paragraph(html),
+            SummaryMode::Words => truncate_html(html, site.summary_length.unwrap_or(50), true),
+            SummaryMode::Characters => {
+                truncate_html(html, site.summary_length.unwrap_or(200), false)
+            }
+        })
+    }
+
+    fn render(&self, md: &str) -> Result<(String, Vec<Heading>)> {
+        let (md, shortcodes) = self.expand(md)?;
+        let parser = Parser::new_ext(&md, Options::all());
+
+        let mut events = Vec::new();
+        let mut code_block: Option<(CowStr, String)> = None;
+        let mut heading: Option<Vec<Event>> = None;
+        let mut image: Option<(CowStr, CowStr, String)> = None;
+        let mut headings = Vec::new();
+        let mut ids = HashSet::new();
+
+        for event in insert_shortcodes(merge_text(parser).into_iter().peekable(), &shortcodes) {
+            let event = match (event, &mut image) {
+                (Event::Start(Tag::Image(_, src, title)), None) => {
+                    image = Some((src, title, String::new()));
+                    continue;
+                }
+                (Event::Start(Tag::Link(link_type, dest, title)), None) => {
+                    let dest = self
+                        .resolve_link(&dest, ProblemKind::BrokenLink)
+                        .map_or(dest, CowStr::from);
+                    Event::Start(Tag::Link(link_type, dest, title))
+                }
+                (Event::Text(text) | Event::Code(text), Some((_, _, alt))) => {
+                    alt.push_str(&text);
+                    continue;
+                }
+                (Event::End(Tag::Image(..)), Some((src, title, alt))) => {
+                    let title = Some(title.as_ref()).filter(|title| !title.is_empty());
+                    let html = self.render_image(src, alt, title);
+                    image = None;
+                    Event::Html(html.into())
+                }
+                (_, Some(_)) => continue,
+                (event, None) => event,
+            };
+
+            match (event, &mut code_block, &mut heading) {
+                (Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))), _, _) => {
+                    code_block = Some((info, String::new()))
+                }
+                (Event::Text(text), Some((_, code)), _) => code.push_str(&text),
+                (Event::End(Tag::CodeBlock(CodeBlockKind::Fenced(_))), Some((info, code)), _) => {
+                    events.push(Event::Html(highlight::highlight(info, code).into()));
+                    code_block = None;
+                }
+                (Event::Start(Tag::Heading(_)), _, None) => heading = Some(Vec::new()),
+                (Event::End(Tag::Heading(level)), _, Some(inner)) => {
+                    let (html, rendered) = render_heading(level, std::mem::take(inner), &mut ids);
+                    events.push(Event::Html(html.into()));
+                    headings.push(rendered);
+                    heading = None;
+                }
+                (event, _, Some(inner)) => inner.push(event),
+                (event, _, None) => events.push(event),
+            }
+        }
+
+        let mut output = String::new();
+        html::push_html(&mut output, events.into_iter());
+
+        Ok((output, headings))
+    }
+
+    fn expand(&self, md: &str) -> Result<(String, Vec<String>)> {
+        let code_ranges = Parser::new_ext(md, Options::all())
+            .into_offset_iter()
+            .filter_map(|(event, range)| match event {
+                Event::Start(Tag::CodeBlock(_)) | Event::Code(_) => Some(range),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let mut output = String::new();
+        let mut rendered = Vec::new();
+        let mut pos = 0;
+
+        let math = Config::read().markdown.math;
+
+        while let Some(start) = md[pos..]
+            .match_indices(|c| c == '{' || (math && c == '$'))
+            .map(|(idx, _)| pos + idx)
+            .find(|start| md[*start..].starts_with('$') || md[*start..].starts_with("{{<"))
+        {
+            if let Some(range) = code_ranges.iter().find(|range| range.contains(&start)) {
+                output.push_str(&md[pos..range.end]);
+                pos = range.end;
+                continue;
+            }
+
+            if md[start..].starts_with('$') {
+                let escaped = md[..start].chars().rev().take_while(|c| *c == '\\').count() % 2 == 1;
+
+                match read_math(md, start).filter(|_| !escaped) {
+                    Some((tex, end, display)) => {
+                        output.push_str(&md[pos..start]);
+                        pos = end;
+                        let is_block = display && is_own_line(md, start, end);
+                        push_placeholder(
+                            &mut output,
+                            &mut rendered,
+                            math::to_mathml(tex, display),
+                            is_block,
+                        );
+                    }
+                    None => {
+                        let end =
+                            start + md[start..].find(|c| c != '$').unwrap_or(md.len() - start);
+                        output.push_str(&md[pos..end]);
+                        pos = end;
+                    }
+                }
+                continue;
+            }
+
+            output.push_str(&md[pos..start]);
+
+            let (inner, end) = match read_tag(md, start) {
+                Ok(tag) => tag,
+                Err(err) => {
+                    pos = start + 3;
+                    let html = self.shortcode_error(&md[start..pos], err);
+                    push_placeholder(&mut output, &mut rendered, html, false);
+                    continue;
+                }
+            };
+            pos = end;
+
+            if let Some(literal) = inner
+                .strip_prefix("/*")
+                .and_then(|inner| inner.strip_suffix("*/"))
+            {
+                output.push_str(&format!("{{{{<{}>}}}}", literal));
+                continue;
+            }
+
+            let html = match inner.strip_prefix('/') {
+                Some(name) => Err(anyhow!(
+                    "Unexpected closing shortcode: {{{{< /{} >}}}}",
+                    name.trim()
+                )),
+                None => parse_args(inner).and_then(|(name, args)| {
+                    let body = match find_closing(md, end, name, &code_ranges)? {
+                        Some((body, close_end)) => {
+                            pos = close_end;
+                            Some(body)
+                        }
+                        None => None,
+                    };
+
+                    self.render_shortcode(&Shortcode {
+                        name,
+                        args,
+                        body,
+                        is_block: is_own_line(md, start, pos),
+                    })
+                }),
+            };
+
+            let is_block = is_own_line(md, start, pos);
+            let html = html.unwrap_or_else(|err| self.shortcode_error(&md[start..pos], err));
+
+            push_placeholder(&mut output, &mut rendered, html, is_block);
+        }
+
+        output.push_str(&md[pos..]);
+
+        Ok((output, rendered))
+    }
+
+    fn shortcode_error(&self, source: &str, err: Error) -> String {
+        let message = err.to_string();
+        let html = format!(
+            r#"<span class="shortcode-error" title="{}">{}</span>"#,
+            html_escape::escape(&message),
+            html_escape::escape(source)
+        );
+
+        self.problems.lock().unwrap().push(Problem::new(
+            self.path,
+            ProblemKind::InvalidShortcode,
+            message,
+        ));
+
+        html
+    }
+
+    fn render_shortcode(&self, shortcode: &Shortcode) -> Result<String> {
+        let Shortcode {
+            name,
+            args,
+            body,
+            is_block,
+        } = shortcode;
+        let mut body = match body {
+            Some(body) => self.render(body)?.0,
+            None => String::new(),
+        };
+
+        if !is_block && body.matches("<p>").count() == 1 {
+            if let Some(inline) = body
+                .strip_prefix("<p>")
+                .and_then(|body| body.strip_suffix("</p>\n"))
+            {
+                body = inline.to_owned();
+            }
+        }
+
+        if let Some(template) = self.shortcodes.get(*name) {
+            let result = SHORTCODE_PARAM
+                .get_or_init(|| Regex::new(r"\{:shortcode\.([A-Za-z0-9_]+)\}").unwrap())
+                .replace_all(template, |cap: &Captures| match &cap[1] {
+                    "body" => body.clone(),
+                    arg => args
+                        .get(arg)
+                        .map(|value| html_escape::escape(value))
+                        .unwrap_or_default(),
+                });
+            return Ok(result.into_owned());
+        }
+
+        let arg = |key: &str, idx: usize| {
+            args.get(key)
+                .or_else(|| args.get(&idx.to_string()))
+                .map(String::as_str)
+        };
+        let required = |key: &str, idx: usize| {
+            arg(key, idx)
+                .ok_or_else(|| anyhow!("Shortcode `{}` requires the `{}` argument", name, key))
+        };
+
+        let html = match *name {
+            "youtube" => format!(
+                r#"<div class="video"><iframe src="https://www.youtube-nocookie.com/embed/{}" title="{}" allow="fullscreen" loading="lazy"></iframe></div>"#,
+                html_escape::escape(required("id", 0)?),
+                html_escape::escape(arg("title", 1).unwrap_or("YouTube video")),
+            ),
+            "video" => {
+                let mut html = format!(
+                    r#"<video src="{}" controls preload="metadata""#,
+                    html_escape::escape(required("src", 0)?)
+                );
+                if let Some(poster) = arg("poster", 1) {
+                    html.push_str(&format!(r#" poster="{}""#, html_escape::escape(poster)));
+                }
+                html.push_str("></video>");
+                html
+            }
+            "figure" => {
+                let mut html = String::from("<figure>");
+                html.push_str(&self.render_image(
+                    required("src", 0)?,
+                    arg("alt", 2).or_else(|| arg("caption", 1)).unwrap_or(""),
+                    None,
+                ));
+                if let Some(caption) = arg("caption", 1) {
+                    html.push_str(&format!(
+                        "<figcaption>{}</figcaption>",
+                        html_escape::escape(caption)
+                    ));
+                }
+                html.push_str("</figure>");
+                html
+            }
+            "include" => {
+                self.render_include(required("file", 0)?, arg("lang", 1), arg("lines", 2))?
+            }
+            "note" | "tip" | "warning" => {
+                let default_title = match *name {
+                    "note" => "Note",
+                    "tip" => "Tip",
+                    _ => "Warning",
+                };
+                format!(
+                    r#"<div class="callout callout-{}"><p class="callout-title">{}</p>{}</div>"#,
+                    name,
+                    html_escape::escape(arg("title", 0).unwrap_or(default_title)),
+                    body,
+                )
+            }
+            _ => bail!("Unknown shortcode: {{{{< {} >}}}}", name),
+        };
+
+        Ok(html)
+    }
+
+    fn resolve_link(&self, dest: &str, kind: ProblemKind) -> Option<String> {
+        let is_external = dest.starts_with(['/', '#', '?'])
+            || dest
+                .split(['/', '?', '#'])
+                .next()
+                .is_some_and(|segment| segment.contains(':'));
+
+        if dest.is_empty() || is_external {
+            return None;
+        }
+
+        let split = dest.find(['?', '#']).unwrap_or(dest.len());
+        let (target, suffix) = dest.split_at(split);
+
+        let mut path = self
+            .path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        for component in Path::new(target).components() {
+            match component {
+                Component::Normal(segment) => path.push(segment),
+                Component::ParentDir if path.pop() => {}
+                Component::CurDir => {}
+                _ => {
+                    self.report(kind, dest);
+                    return None;
+                }
+            }
+        }
+
+        if format::from_path(&path).is_some() {
+            match self.links.get(&path) {
+                Some(url) => Some(format!("{}{}", url, suffix)),
+                None => {
+                    self.report(kind, dest);
+                    None
+                }
+            }
+        } else if self.root.join(&path).exists() {
+            Some(format!(
+                "{}/{}{}",
+                Config::read().site.url,
+                path.to_string_lossy(),
+                suffix
+            ))
+        } else {
+            self.report(kind, dest);
+            None
+        }
+    }
+
+    fn report(&self, kind: ProblemKind, dest: &str) {
+        let message = match kind {
+            ProblemKind::MissingImage => format!("Missing image: {}", dest),
+            _ => format!("Broken link: {}", dest),
+        };
+
+        self.problems
+            .lock()
+            .unwrap()
+            .push(Problem::new(self.path, kind, message));
+    }
+
+    fn render_image(&self, src: &str, alt: &str, title: Option<&str>) -> String {
+        let mut html = String::new();
+        let src = self
+            .resolve_link(src, ProblemKind::MissingImage)
+            .map_or(Cow::Borrowed(src), Cow::Owned);
+        let src = src.as_ref();
+
+        let config = Config::read();
+        let local = src.strip_prefix(&config.site.url).unwrap_or(src);
+        let local = local.split(['?', '#']).next().unwrap();
+        let is_generated = local.starts_with(&config.images.prefix)
+            || config
+                .theme
+                .as_ref()
+                .is_some_and(|theme| local.starts_with(&theme.static_prefix));
+
+        if local.starts_with('/')
+            && !local.starts_with("//")
+            && !is_generated
+            && !self.root.join(&local[1..]).exists()
+        {
+            self.report(ProblemKind::MissingImage, src);
+        }
+        let image = self.images.get(src);
+
+        if let Some(webp_srcset) = image.and_then(|image| image.webp_srcset.as_ref()) {
+            html.push_str(r#"<picture><source type="image/webp" srcset=""#);
+            html_escape::push_escaped(&mut html, webp_srcset);
+            html.push_str(&format!(r#"" sizes="{}">"#, sizes(image.unwrap().width)));
+        }
+
+        html.push_str(r#"<img src=""#);
+        html_escape::push_escaped(&mut html, src);
+        html.push_str(r#"" alt=""#);
+        html_escape::push_escaped(&mut html, alt);
+        html.push('"');
+
+        if let Some(title) = title {
+            html.push_str(r#" title=""#);
+            html_escape::push_escaped(&mut html, title);
+            html.push('"');
+        }
+
+        if let Some(image) = image {
+            html.push_str(&format!(
+                r#" width="{}" height="{}""#,
+                image.width, image.height
+            ));
+
+            if let Some(srcset) = &image.srcset {
+                html.push_str(r#" srcset=""#);
+                html_escape::push_escaped(&mut html, srcset);
+                html.push_str(&format!(r#"" sizes="{}""#, sizes(image.width)));
+            }
+        }
+
+        html.push_str(r#" loading="lazy" decoding="async">"#);
+
+        if image.is_some_and(|image| image.webp_srcset.is_some()) {
+            html.push_str("</picture>");
+        }
+
+        html
+    }
+
+    fn render_include(
+        &self,
+        file: &str,
+        lang: Option<&str>,
+        lines: Option<&str>,
+    ) -> Result<String> {
+        let path = Path::new(file);
+
+        if !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            bail!(
+                "Included file must be a relative path inside the repository: {}",
+                file
+            );
+        }
+
+        let code = std::fs::read_to_string(self.root.join(path))
+            .map_err(|err| anyhow!("Failed to include {}: {}", file, err))?;
+
+        let code = match lines {
+            Some(lines) => {
+                let (first, last) = lines.split_once('-').unwrap_or((lines, lines));
+                let parse = |num: &str, default: usize| match num.trim() {
+                    "" => Ok(default),
+                    num => num
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|num| *num > 0)
+                        .ok_or_else(|| {
+                            anyhow!(
+                                "Invalid line range of the included file {}: {}",
+                                file,
+                                lines
+                            )
+                        }),
+                };
+                let (first, last) = (parse(first, 1)?, parse(last, usize::MAX)?);
+
+                code.lines()
+                    .skip(first - 1)
+                    .take(last.saturating_sub(first - 1))
+                    .map(|line| format!("{}\n", line))
+                    .collect()
+            }
+            None => code,
+        };
+
+        let lang = lang.or_else(|| path.extension().and_then(|ext| ext.to_str()));
+
+        Ok(highlight::highlight(lang.unwrap_or(""), &code))
+    }
+}
+
+fn sizes(width: u32) -> String {
+    format!("(max-width: {0}px) 100vw, {0}px", width)
+}
+
+fn push_placeholder(output: &mut String, rendered: &mut Vec<String>, html: String, is_block: bool) {
+    if is_block {
+        output.push_str("\n\n");
+    }
+    output.push(PLACEHOLDER);
+    output.push_str(&rendered.len().to_string());
+    output.push(PLACEHOLDER);
+    if is_block {
+        output.push_str("\n\n");
+    }
+
+    rendered.push(html);
+}
+
+fn is_own_line(md: &str, start: usize, end: usize) -> bool {
+    (start == 0 || md[..start].ends_with('\n'))
+        && md[end..]
+            .lines()
+            .next()
+            .is_none_or(|rest| rest.trim().is_empty())
+}
+
+fn read_math(md: &str, start: usize) -> Option<(&str, usize, bool)> {
+    if let Some(rest) = md[start..].strip_prefix("$$") {
+        let len = rest.find("$$")?;
+        let tex = &rest[..len];
+        return Some((tex, start + 2 + len + 2, true)).filter(|_| !tex.trim().is_empty());
+    }
+
+    let rest = &md[start + 1..];
+    if rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let len = rest.find('$')?;
+    let tex = &rest[..len];
+
+    let is_closing = !tex.is_empty()
+        && !tex.contains("\n\n")
+        && !tex.ends_with(char::is_whitespace)
+        && !tex.ends_with('\\')
+        && !rest[len + 1..].starts_with(|c: char| c.is_ascii_digit());
+
+    Some((tex, start + 1 + len + 1, false)).filter(|_| is_closing)
+}
+
+fn read_tag(md: &str, start: usize) -> Result<(&str, usize)> {
+    match md[start..].find(">}}") {
+        Some(len) => Ok((md[start + 3..start + len].trim(), start + len + 3)),
+        None => bail!(
+            "Shortcode is not closed by `>}}}}`: {}",
+            md[start..].lines().next().unwrap_or("")
+        ),
+    }
+}
+
+fn find_closing<'m>(
+    md: &'m str,
+    mut pos: usize,
+    name: &str,
+    code_ranges: &[Range<usize>],
+) -> Result<Option<(&'m str, usize)>> {
+    let body_start = pos;
+    let mut depth = 0;
+
+    while let Some(start) = md[pos..].find("{{<").map(|idx| pos + idx) {
+        if let Some(range) = code_ranges.iter().find(|range| range.contains(&start)) {
+            pos = range.end;
+            continue;
+        }
+
+        let (inner, end) = read_tag(md, start)?;
+        pos = end;
+
+        if let Some(closing) = inner.strip_prefix('/') {
+            if closing.trim() == name {
+                if depth == 0 {
+                    return Ok(Some((&md[body_start..start], end)));
+                }
+                depth -= 1;
+            }
+        } else if inner.split_whitespace().next() == Some(name) {
+            depth += 1;
+        }
+    }
+
+    Ok(None)
+}
+
+fn parse_args(inner: &str) -> Result<(&str, HashMap<String, String>)> {
+    let name_end = inner.find(char::is_whitespace).unwrap_or(inner.len());
+    let name = &inner[..name_end];
+
+    if name.is_empty() {
+        bail!("Shortcode without a name: {{{{<{}>}}}}", inner);
+    }
+
+    let mut args = HashMap::new();
+    let mut chars = inner[name_end..].chars().peekable();
+    let mut positional = 0;
+
+    let read_value = |chars: &mut Peekable<std::str::Chars>| -> Result<String> {
+        let mut value = String::new();
+
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => return Ok(value),
+                    Some('\\') => value.extend(chars.next()),
+                    Some(c) => value.push(c),
+                    None => bail!("Unterminated string in shortcode `{}`", name),
+                }
+            }
+        }
+
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != '=') {
+            value.push(c);
+        }
+
+        Ok(value)
+    };
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        if chars.peek().is_none() {
+            return Ok((name, args));
+        }
+
+        let key = read_value(&mut chars)?;
+
+        if chars.next_if_eq(&'=').is_some() {
+            args.insert(key, read_value(&mut chars)?);
+        } else {
+            args.insert(positional.to_string(), key);
+            positional += 1;
+        }
+    }
+}
+
+fn merge_text<'e>(events: impl Iterator<Item = Event<'e>>) -> Vec<Event<'e>> {
+    let mut merged: Vec<Event> = Vec::new();
+
+    for event in events {
+        match (merged.last_mut(), event) {
+            (Some(Event::Text(prev)), Event::Text(text)) => {
+                *prev = format!("{}{}", prev, text).into()
+            }
+            (_, event) => merged.push(event),
+        }
+    }
+
+    merged
+}
+
+fn insert_shortcodes<'e>(
+    mut events: Peekable<impl Iterator<Item = Event<'e>>>,
+    shortcodes: &[String],
+) -> Vec<Event<'e>> {
+    let mut result = Vec::new();
+
+    while let Some(event) = events.next() {
+        let text = match event {
+            Event::Text(text) if text.contains(PLACEHOLDER) => text,
+            event => {
+                result.push(event);
+                continue;
+            }
+        };
+
+        let is_block = matches!(result.last(), Some(Event::Start(Tag::Paragraph)))
+            && matches!(events.peek(), Some(Event::End(Tag::Paragraph)))
+            && text.starts_with(PLACEHOLDER)
+            && text.ends_with(PLACEHOLDER)
+            && text.matches(PLACEHOLDER).count() == 2;
+
+        if is_block {
+            result.pop();
+            events.next();
+        }
+
+        for (idx, segment) in text.split(PLACEHOLDER).enumerate() {
+            if idx % 2 == 0 {
+                if !segment.is_empty() {
+                    result.push(Event::Text(segment.to_owned().into()));
+                }
+            } else {
+                let html = segment
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|idx| shortcodes.get(idx));
+                match html {
+                    Some(html) => result.push(Event::Html(html.clone().into())),
+                    None => result.push(Event::Text(segment.to_owned().into())),
+                }
+            }
+        }
+    }
+
+    result
+}
+
+fn render_heading(
+    level: u32,
+    mut inner: Vec<Event>,
+    ids: &mut HashSet<String>,
+) -> (String, Heading) {
+    let mut custom_id = None;
+
+    if let Some(Event::Text(text)) = inner.last_mut() {
+        if let Some((rest, id)) = text
+            .strip_suffix('}')
+            .and_then(|text| text.rsplit_once("{#"))
+            .filter(|(_, id)| !id.is_empty() && !id.contains(char::is_whitespace))
+        {
+            custom_id = Some(id.to_owned());
+            *text = rest.trim_end().to_owned().into();
+        }
+    }
+
+    let title = inner
+        .iter()
+        .filter_map(|event| match event {
+            Event::Text(text) | Event::Code(text) => Some(text.as_ref()),
+            _ => None,
+        })
+        .collect::<String>();
+
+    let base_id = custom_id.unwrap_or_else(|| slugify(&title));
+    let mut id = base_id.clone();
+    let mut count = 0;
+    while !ids.insert(id.clone()) {
+        count += 1;
+        id = format!("{}-{}", base_id, count);
+    }
+
+    let mut result = format!("<h{} id=\"", level);
+    html_escape::push_escaped(&mut result, &id);
+    result.push_str("\">");
+    html::push_html(&mut result, inner.into_iter());
+
+    if Config::read().markdown.heading_anchors {
+        result.push_str(r##" <a class="anchor" href="#"##);
+        html_escape::push_escaped(&mut result, &id);
+        result.push_str(r#"" aria-hidden="true">#</a>"#);
+    }
+
+    result.push_str(&format!("</h{}>\n", level));
+
+    (result, Heading { level, id, title })
+}
+
+fn slugify(title: &str) -> String {
+    let mut slug = String::new();
+
+    for c in title.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() || c == '_' {
+            slug.push(c);
+        } else if (c.is_whitespace() || c == '-') && !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    let slug = slug.trim_end_matches('-');
+
+    if slug.is_empty() {
+        String::from("section")
+    } else {
+        slug.to_owned()
+    }
+}
+
+fn gen_toc(headings: &[Heading]) -> String {
+    let min_level = match headings.iter().map(|heading| heading.level).min() {
+        Some(min_level) => min_level,
+        None => return String::new(),
+    };
+    let max_level = min_level + Config::read().markdown.toc_depth as u32;
+
+    let mut result = String::new();
+    let mut levels: Vec<u32> = Vec::new();
+
+    for heading in headings.iter().filter(|heading| heading.level < max_level) {
+        while levels.len() > 1 && heading.level < *levels.last().unwrap() {
+            result.push_str("</li></ul>");
+            levels.pop();
+        }
+
+        match levels.last_mut() {
+            None => {
+                result.push_str(r#"<ul id="toc"><li>"#);
+                levels.push(heading.level);
+            }
+            Some(level) if heading.level > *level => {
+                result.push_str("<ul><li>");
+                levels.push(heading.level);
+            }
+            Some(level) => {
+                result.push_str("</li><li>");
+                *level = heading.level;
+            }
+        }
+
+        result.push_str(r##"<a href="#"##);
+        html_escape::push_escaped(&mut result, &heading.id);
+        result.push_str(r#"">"#);
+        html_escape::push_escaped(&mut result, &heading.title);
+        result.push_str("</a>");
+    }
+
+    levels.iter().for_each(|_| result.push_str("</li></ul>"));
+
+    result
+}
+
+fn first_paragraph(html: &str) -> (String, bool) {
+    let start = html.find("<p>").or_else(|| html.find("<p "));
+    let end = start.and_then(|start| html[start..].find("</p>").map(|end| start + end + 4));
+
+    match (start, end) {
+        (Some(start), Some(end)) => {
+            let has_more = !html[..start].trim().is_empty() || !html[end..].trim().is_empty();
+            (html[start..end].to_owned(), has_more)
+        }
+        _ => (html.to_owned(), false),
+    }
+}
+
+enum HtmlToken<'h> {
+    Open(&'h str, String),
+    Close(&'h str, String),
+    Other(&'h str),
+    Text(&'h str),
+}
+
+struct HtmlTokens<'h> {
+    rest: &'h str,
+    hidden: Option<String>,
+}
+
+impl<'h> HtmlTokens<'h> {
+    const VOID_ELEMENTS: [&'static str; 13] = [
+        "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source",
+        "track", "wbr",
+    ];
+    const HIDDEN_ELEMENTS: [&'static str; 3] = ["script", "style", "annotation"];
+
+    fn new(html: &'h str) -> Self {
+        Self {
+            rest: html,
+            hidden: None,
+        }
+    }
+
+    fn take(&mut self, len: usize) -> &'h str {
+        let (taken, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        taken
+    }
+}
+
+impl<'h> Iterator for HtmlTokens<'h> {
+    type Item = HtmlToken<'h>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(closing) = self.hidden.take() {
+            let len = self.rest.find(&closing).unwrap_or(self.rest.len());
+            if len > 0 {
+                return Some(HtmlToken::Other(self.take(len)));
+            }
+        }
+
+        let c = self.rest.chars().next()?;
+
+        if c == '<' {
+            let len = if self.rest.starts_with("<!--") {
+                self.rest.find("-->").map_or(self.rest.len(), |end| end + 3)
+            } else {
+                self.rest.find('>').map_or(self.rest.len(), |end| end + 1)
+            };
+            let tag = self.take(len);
+            let name = tag
+                .trim_start_matches(['<', '/'])
+                .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+                .next()
+                .unwrap()
+                .to_ascii_lowercase();
+
+            return Some(if tag.starts_with("</") {
+                HtmlToken::Close(tag, name)
+            } else if !name.starts_with(|c: char| c.is_ascii_alphabetic())
+                || tag.ends_with("/>")
+                || Self::VOID_ELEMENTS.contains(&name.as_str())
+            {
+                HtmlToken::Other(tag)
+            } else {
+                if Self::HIDDEN_ELEMENTS.contains(&name.as_str()) {
+                    self.hidden = Some(format!("</{}>", name));
+                }
+                HtmlToken::Open(tag, name)
+            });
+        }
+
+        let len = match c {
+            '&' => self
+                .rest
+                .find(';')
+                .filter(|end| *end < 10)
+                .map_or(1, |end| end + 1),
+            c => c.len_utf8(),
+        };
+
+        Some(HtmlToken::Text(self.take(len)))
+    }
+}
+
+fn truncate_html(html: &str, limit: usize, count_words: bool) -> (String, bool) {
+    let mut result = String::with_capacity(html.len());
+    let mut open = Vec::new();
+    let mut checkpoint = (0, Vec::new());
+    let mut count = 0;
+    let mut in_word = false;
+
+    for token in HtmlTokens::new(html) {
+        match token {
+            HtmlToken::Open(tag, name) => {
+                result.push_str(tag);
+                open.push(name);
+            }
+            HtmlToken::Close(tag, name) => {
+                result.push_str(tag);
+                if let Some(idx) = open.iter().rposition(|open| open == &name) {
+                    open.truncate(idx);
+                }
+            }
+            HtmlToken::Other(raw) => result.push_str(raw),
+            HtmlToken::Text(text) if text.starts_with(char::is_whitespace) => {
+                in_word = false;
+                result.push_str(text);
+            }
+            HtmlToken::Text(text) => {
+                if count_words {
+                    count += usize::from(!in_word);
+                } else {
+                    count += 1 + usize::from(!in_word && count > 0);
+                }
+                in_word = true;
+
+                if count > limit {
+                    let (len, open) = checkpoint;
+                    result.truncate(len);
+                    result.push('…');
+                    for name in open.iter().rev() {
+                        result.push_str(&format!("</{}>", name));
+                    }
+                    return (result, true);
+                }
+
+                result.push_str(text);
+                checkpoint.0 = result.len();
+                checkpoint.1.clone_from(&open);
+            }
+        }
+    }
+
+    (result, false)
+}
+
+pub fn html_to_text(html: &str) -> String {
+    const INLINE_ELEMENTS: [&str; 16] = [
+        "a", "abbr", "b", "code", "del", "em", "i", "kbd", "mark", "s", "small", "span", "strong",
+        "sub", "sup", "u",
+    ];
+
+    let mut result = String::with_capacity(html.len());
+    let mut hidden = false;
+
+    for token in HtmlTokens::new(html) {
+        match token {
+            HtmlToken::Open(_, name) => {
+                hidden = HtmlTokens::HIDDEN_ELEMENTS.contains(&name.as_str())
+            }
+            HtmlToken::Close(_, name) => {
+                hidden = false;
+                if !INLINE_ELEMENTS.contains(&name.as_str()) {
+                    result.push('\n');
+                }
+            }
+            HtmlToken::Other(_) if hidden => {}
+            HtmlToken::Other(tag) => {
+                if tag.starts_with("<br") || tag.starts_with("<img") {
+                    result.push(' ');
+                }
+            }
+            HtmlToken::Text(text) => match text {
+                "&amp;" => result.push('&'),
+                "&lt;" => result.push('<'),
+                "&gt;" => result.push('>'),
+                "&quot;" => result.push('"'),
+                "&#39;" => result.push('\''),
+                text if text.starts_with('&') && text.len() > 1 => result.push(' '),
+                text => result.push_str(text),
+            },
+        }
+    }
+
+    result
+}
+
+pub fn md_to_text(md: &str) -> String {
     let parser = Parser::new_ext(md, Options::all());
 
     let mut output = String::new();
-    html::push_html(&mut output, parser);
+
+    for event in parser {
+        match event {
+            Event::Text(text) | Event::Code(text) => output.push_str(&text),
+            Event::SoftBreak | Event::HardBreak => output.push(' '),
+            Event::End(
+                Tag::Paragraph
+                | Tag::Heading(_)
+                | Tag::BlockQuote
+                | Tag::CodeBlock(_)
+                | Tag::Item
+                | Tag::TableCell,
+            ) => output.push('\n'),
+            _ => {}
+        }
+    }
 
     output
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::template::tests::with_renderer;
+
+    fn render(md: &str) -> (String, Vec<Problem>) {
+        with_renderer(|renderer| renderer.md_to_html(md).unwrap())
+    }
+
+    #[test]
+    fn parse_shortcode_args() {
+        let (name, args) = parse_args(r#"figure src="/a b.png" 42 alt="say \"hi\"""#).unwrap();
+        assert_eq!(name, "figure");
+        assert_eq!(args["src"], "/a b.png");
+        assert_eq!(args["0"], "42");
+        assert_eq!(args["alt"], r#"say "hi""#);
+
+        let (name, args) = parse_args("note").unwrap();
+        assert_eq!(name, "note");
+        assert!(args.is_empty());
+    }
+
+    #[test]
+    fn parse_invalid_shortcode_args() {
+        assert!(parse_args("").is_err());
+        assert!(parse_args(r#"figure src="/a.png"#).is_err());
+    }
+
+    #[test]
+    fn read_tags() {
+        let md = "a {{< note >}} b {{< /note >}}";
+        assert_eq!(read_tag(md, 2).unwrap(), ("note", 14));
+        assert_eq!(
+            find_closing(md, 14, "note", &[]).unwrap(),
+            Some((" b ", md.len()))
+        );
+        assert_eq!(find_closing(md, md.len(), "note", &[]).unwrap(), None);
+        assert!(read_tag("{{< note", 0).is_err());
+    }
+
+    #[test]
+    fn invalid_shortcodes_are_reported() {
+        for md in [
+            "{{< nope >}}",
+            "{{< /note >}}",
+            r#"{{< figure src="/a.png >}}"#,
+            r#"{{< include "missing.txt" >}}"#,
+            "text {{< note",
+        ] {
+            let (html, problems) = render(md);
+            assert!(
+                html.contains(r#"<span class="shortcode-error" title=""#),
+                "{}",
+                html
+            );
+            assert_eq!(problems.len(), 1, "{}", md);
+            assert_eq!(problems[0].kind, ProblemKind::InvalidShortcode);
+            assert_eq!(problems[0].file, "posts/test.md");
+        }
+    }
+
+    #[test]
+    fn truncate_by_words() {
+        assert_eq!(
+            truncate_html("<p>one <em>two three</em> four</p>", 2, true),
+            (String::from("<p>one <em>two…</em></p>"), true)
+        );
+        assert_eq!(
+            truncate_html("<p>a &amp; b</p>", 2, true),
+            (String::from("<p>a &amp;…</p>"), true)
+        );
+        assert_eq!(
+            truncate_html("<p>one two</p>", 2, true),
+            (String::from("<p>one two</p>"), false)
+        );
+    }
+
+    #[test]
+    fn truncate_by_characters() {
+        assert_eq!(
+            truncate_html("<p><a href=\"/x\">abc</a> def</p>", 5, false),
+            (String::from("<p><a href=\"/x\">abc</a> d…</p>"), true)
+        );
+        assert_eq!(truncate_html("", 5, false), (String::new(), false));
+    }
+
+    #[test]
+    fn summary_first_paragraph() {
+        assert_eq!(
+            first_paragraph("<h2>Title</h2>\n<p>a</p>\n<p>b</p>\n"),
+            (String::from("<p>a</p>"), true)
+        );
+        assert_eq!(
+            first_paragraph("<p>a</p>\n"),
+            (String::from("<p>a</p>"), false)
+        );
+        assert_eq!(
+            first_paragraph("<ul><li>a</li></ul>"),
+            (String::from("<ul><li>a</li></ul>"), false)
+        );
+    }
+
+    #[test]
+    fn text_from_html() {
+        assert_eq!(
+            html_to_text("<p>a &amp; <em>b</em></p><script>x()</script><p>c<br>d</p>"),
+            "a & b\n\nc d\n"
+        );
+    }
+
+    #[test]
+    fn rendering_continues_after_invalid_shortcode() {
+        let (html, problems) = render("{{< nope >}}\n\n*after* {{</* nope */>}}");
+        assert!(
+            html.contains("<em>after</em> {{&lt; nope &gt;}}"),
+            "{}",
+            html
+        );
+        assert_eq!(problems.len(), 1);
+    }
+}
@@ -0,0 +1,959 @@
+use super::html::escape;
+use anyhow::{anyhow, bail, Result};
+
+struct MathParser {
+    chars: Vec<char>,
+    pos: usize,
+    variant: Option<Variant>,
+}
+
+#[derive(Clone, Copy)]
+enum Variant {
+    Normal,
+    Bold,
+    Italic,
+    BoldItalic,
+    Script,
+    Fraktur,
+    DoubleStruck,
+    SansSerif,
+    Monospace,
+}
+
+enum End {
+    Eof,
+    Brace,
+    Bracket,
+    Right,
+    Cell,
+    Row,
+    Env(String),
+}
+
+struct Atom {
+    mathml: String,
+    limits: bool,
+}
+
+impl Atom {
+    fn new(mathml: String) -> Self {
+        Self {
+            mathml,
+            limits: false,
+        }
+    }
+}
+
+pub fn to_mathml(tex: &str, display: bool) -> String {
+    let mut parser = MathParser {
+        chars: tex.chars().collect(),
+        pos: 0,
+        variant: None,
+    };
+
+    let result = parser.parse_row(false).and_then(|(row, end)| match end {
+        End::Eof => Ok(row),
+        end => Err(parser.unexpected(end)),
+    });
+
+    match result {
+        Ok(row) => format!(
+            r#"<math xmlns="http://www.w3.org/1998/Math/MathML" display="{}"><semantics>{}<annotation encoding="application/x-tex">{}</annotation></semantics></math>"#,
+            if display { "block" } else { "inline" },
+            row,
+            escape(tex.trim()),
+        ),
+        Err(err) => {
+            let delimiter = if display { "$$" } else { "$" };
+            format!(
+                r#"<span class="math-error" title="{}">{}{}{}</span>"#,
+                escape(&err.to_string()),
+                delimiter,
+                escape(tex),
+                delimiter,
+            )
+        }
+    }
+}
+
+impl MathParser {
+    fn parse_row(&mut self, in_bracket: bool) -> Result<(String, End)> {
+        let mut row = String::new();
+
+        loop {
+            self.skip_whitespace();
+
+            let end = match self.peek() {
+                None => Some(End::Eof),
+                Some('}') => {
+                    self.pos += 1;
+                    Some(End::Brace)
+                }
+                Some(']') if in_bracket => {
+                    self.pos += 1;
+                    Some(End::Bracket)
+                }
+                Some('&') => {
+                    self.pos += 1;
+                    Some(End::Cell)
+                }
+                Some('\\') => match self.peek_command().as_str() {
+                    "\\" => {
+                        self.pos += 2;
+                        Some(End::Row)
+                    }
+                    "right" => {
+                        self.pos += 6;
+                        Some(End::Right)
+                    }
+                    "end" => {
+                        self.pos += 4;
+                        Some(End::Env(self.parse_text_arg()?))
+                    }
+                    style @ ("displaystyle" | "textstyle") => {
+                        self.pos += style.len() + 1;
+                        let (rest, end) = self.parse_row(in_bracket)?;
+                        row.push_str(&format!(
+                            r#"<mstyle displaystyle="{}">{}</mstyle>"#,
+                            style == "displaystyle",
+                            rest
+                        ));
+                        Some(end)
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            if let Some(end) = end {
+                return Ok((format!("<mrow>{}</mrow>", row), end));
+            }
+
+            let atom = match self.peek() {
+                Some('_' | '^') => Atom::new(String::from("<mrow></mrow>")),
+                _ => self.parse_atom()?,
+            };
+            row.push_str(&self.parse_scripts(atom)?);
+        }
+    }
+
+    fn parse_scripts(&mut self, mut atom: Atom) -> Result<String> {
+        let mut sub = None;
+        let mut sup = Vec::new();
+        let mut primes = String::new();
+        let mut has_sup = false;
+
+        while self.peek() == Some('\'') {
+            self.pos += 1;
+            primes.push('\u{2032}');
+        }
+        if !primes.is_empty() {
+            sup.push(format!("<mo>{}</mo>", primes));
+        }
+
+        loop {
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some('_') => {
+                    self.pos += 1;
+                    if sub.is_some() {
+                        bail!("Double subscript");
+                    }
+                    sub = Some(self.parse_arg()?);
+                }
+                Some('^') => {
+                    self.pos += 1;
+                    if has_sup {
+                        bail!("Double superscript");
+                    }
+                    has_sup = true;
+                    sup.push(self.parse_arg()?);
+                }
+                Some('\\') if self.peek_command() == "limits" => {
+                    self.pos += 7;
+                    atom.limits = true;
+                }
+                Some('\\') if self.peek_command() == "nolimits" => {
+                    self.pos += 9;
+                    atom.limits = false;
+                }
+                _ => break,
+            }
+        }
+
+        let sup = match sup.len() {
+            0 => None,
+            1 => sup.pop(),
+            _ => Some(format!("<mrow>{}</mrow>", sup.concat())),
+        };
+
+        let (under, over, both) = match atom.limits {
+            true => ("munder", "mover", "munderover"),
+            false => ("msub", "msup", "msubsup"),
+        };
+
+        Ok(match (sub, sup) {
+            (None, None) => atom.mathml,
+            (Some(sub), None) => format!("<{0}>{1}{2}</{0}>", under, atom.mathml, sub),
+            (None, Some(sup)) => format!("<{0}>{1}{2}</{0}>", over, atom.mathml, sup),
+            (Some(sub), Some(sup)) => {
+                format!("<{0}>{1}{2}{3}</{0}>", both, atom.mathml, sub, sup)
+            }
+        })
+    }
+
+    fn parse_arg(&mut self) -> Result<String> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            None | Some('}' | '&' | '_' | '^') => bail!("Missing argument"),
+            Some(c) if c.is_ascii_digit() => {
+                self.pos += 1;
+                Ok(format!("<mn>{}</mn>", self.map_variant(c)))
+            }
+            Some(_) => Ok(self.parse_atom()?.mathml),
+        }
+    }
+
+    fn parse_atom(&mut self) -> Result<Atom> {
+        let c = self.next().ok_or_else(|| anyhow!("Missing argument"))?;
+
+        let mathml = match c {
+            '{' => self.parse_group()?,
+            '\\' => return self.parse_command(),
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut number = String::from(c);
+                while let Some(c) = self.peek().filter(|c| c.is_ascii_digit() || *c == '.') {
+                    number.push(c);
+                    self.pos += 1;
+                }
+                let number = number
+                    .chars()
+                    .map(|c| self.map_variant(c))
+                    .collect::<String>();
+                format!("<mn>{}</mn>", number)
+            }
+            c if c.is_alphabetic() => self.identifier(c),
+            '~' => String::from(r#"<mspace width="0.333em"/>"#),
+            '-' => String::from("<mo>\u{2212}</mo>"),
+            '*' => String::from("<mo>\u{2217}</mo>"),
+            '\'' => String::from("<mo>\u{2032}</mo>"),
+            '#' | '$' | '%' => bail!("Unexpected `{}`", c),
+            c => format!("<mo>{}</mo>", escape(&c.to_string())),
+        };
+
+        Ok(Atom::new(mathml))
+    }
+
+    fn parse_group(&mut self) -> Result<String> {
+        match self.parse_row(false)? {
+            (row, End::Brace) => Ok(row),
+            (_, End::Eof) => bail!("Missing `}}`"),
+            (_, end) => Err(self.unexpected(end)),
+        }
+    }
+
+    fn parse_command(&mut self) -> Result<Atom> {
+        let name = self.read_command();
+
+        if let Some((element, text)) = symbol(&name) {
+            return Ok(Atom::new(format!("<{0}>{1}</{0}>", element, escape(text))));
+        }
+
+        if FUNCTIONS.contains(&name.as_str()) {
+            return Ok(Atom::new(format!("<mi>{}</mi>", name)));
+        }
+
+        if let Some(text) = large_operator(&name) {
+            return Ok(Atom {
+                mathml: format!(r#"<mo movablelimits="true">{}</mo>"#, text),
+                limits: true,
+            });
+        }
+
+        if let Some(text) = integral(&name) {
+            return Ok(Atom::new(format!(r#"<mo largeop="true">{}</mo>"#, text)));
+        }
+
+        if let Some(accent) = accent(&name) {
+            let base = self.parse_arg()?;
+            return Ok(Atom::new(match name.as_str() {
+                "underline" | "underbrace" => format!(
+                    r#"<munder accentunder="true">{}<mo stretchy="true">{}</mo></munder>"#,
+                    base, accent
+                ),
+                _ => format!(
+                    r#"<mover accent="true">{}<mo stretchy="{}">{}</mo></mover>"#,
+                    base,
+                    matches!(
+                        name.as_str(),
+                        "overline" | "overbrace" | "widehat" | "widetilde" | "overrightarrow"
+                    ),
+                    accent
+                ),
+            }));
+        }
+
+        if let Some(variant) = variant(&name) {
+            let previous = self.variant.replace(variant);
+            let arg = self.parse_arg();
+            self.variant = previous;
+            return Ok(Atom::new(arg?));
+        }
+
+        if let Some(size) = big_size(&name) {
+            let delimiter = self.parse_delimiter()?;
+            return Ok(Atom::new(format!(
+                r#"<mo stretchy="true" minsize="{0}" maxsize="{0}">{1}</mo>"#,
+                size, delimiter
+            )));
+        }
+
+        let mathml = match name.as_str() {
+            "frac" | "dfrac" | "tfrac" | "cfrac" => {
+                let numerator = self.parse_arg()?;
+                let denominator = self.parse_arg()?;
+                let frac = format!("<mfrac>{}{}</mfrac>", numerator, denominator);
+                match name.as_str() {
+                    "dfrac" | "cfrac" => {
+                        format!(r#"<mstyle displaystyle="true">{}</mstyle>"#, frac)
+                    }
+                    "tfrac" => format!(r#"<mstyle displaystyle="false">{}</mstyle>"#, frac),
+                    _ => frac,
+                }
+            }
+            "binom" => {
+                let n = self.parse_arg()?;
+                let k = self.parse_arg()?;
+                format!(
+                    r#"<mrow><mo>(</mo><mfrac linethickness="0">{}{}</mfrac><mo>)</mo></mrow>"#,
+                    n, k
+                )
+            }
+            "sqrt" => {
+                self.skip_whitespace();
+                if self.peek() == Some('[') {
+                    self.pos += 1;
+                    let index = match self.parse_row(true)? {
+                        (row, End::Bracket) => row,
+                        (_, End::Eof) => bail!("Missing `]`"),
+                        (_, end) => return Err(self.unexpected(end)),
+                    };
+                    format!("<mroot>{}{}</mroot>", self.parse_arg()?, index)
+                } else {
+                    format!("<msqrt>{}</msqrt>", self.parse_arg()?)
+                }
+            }
+            "overset" | "stackrel" => {
+                let over = self.parse_arg()?;
+                format!("<mover>{}{}</mover>", self.parse_arg()?, over)
+            }
+            "underset" => {
+                let under = self.parse_arg()?;
+                format!("<munder>{}{}</munder>", self.parse_arg()?, under)
+            }
+            "text" | "textrm" | "mbox" => {
+                format!("<mtext>{}</mtext>", escape(&self.parse_text_arg()?))
+            }
+            "operatorname" => format!("<mi>{}</mi>", escape(&self.parse_text_arg()?)),
+            "left" => {
+                let open = self.parse_delimiter()?;
+                let row = match self.parse_row(false)? {
+                    (row, End::Right) => row,
+                    (_, End::Eof) => bail!("`\\left` without `\\right`"),
+                    (_, end) => return Err(self.unexpected(end)),
+                };
+                let close = self.parse_delimiter()?;
+                let fence = |delimiter: &str| match delimiter {
+                    "" => String::new(),
+                    delimiter => format!(r#"<mo fence="true" stretchy="true">{}</mo>"#, delimiter),
+                };
+                format!("<mrow>{}{}{}</mrow>", fence(&open), row, fence(&close))
+            }
+            "middle" => format!(
+                r#"<mo fence="true" stretchy="true">{}</mo>"#,
+                self.parse_delimiter()?
+            ),
+            "not" => {
+                let atom = self.parse_arg()?;
+                match atom.strip_suffix("</mo>") {
+                    Some(operator) => format!("{}\u{338}</mo>", operator),
+                    None => bail!("`\\not` can only be used before a relation"),
+                }
+            }
+            "pmod" => format!(
+                r#"<mrow><mo>(</mo><mo lspace="0">mod</mo>{}<mo>)</mo></mrow>"#,
+                self.parse_arg()?
+            ),
+            "begin" => self.parse_environment()?,
+            "," | "thinspace" => space("0.167em"),
+            ":" | ">" | "medspace" => space("0.222em"),
+            ";" | "thickspace" => space("0.278em"),
+            "!" | "negthinspace" => space("-0.167em"),
+            " " => space("0.333em"),
+            "quad" => space("1em"),
+            "qquad" => space("2em"),
+            "" => bail!("Missing command name after `\\`"),
+            name => bail!("Unknown command: \\{}", name),
+        };
+
+        Ok(Atom::new(mathml))
+    }
+
+    fn parse_environment(&mut self) -> Result<String> {
+        let name = self.parse_text_arg()?;
+
+        let (open, close, align) = match name.as_str() {
+            "matrix" | "smallmatrix" => ("", "", "center"),
+            "pmatrix" => ("(", ")", "center"),
+            "bmatrix" => ("[", "]", "center"),
+            "Bmatrix" => ("{", "}", "center"),
+            "vmatrix" => ("|", "|", "center"),
+            "Vmatrix" => ("\u{2016}", "\u{2016}", "center"),
+            "cases" => ("{", "", "left"),
+            "array" => {
+                self.parse_text_arg()?;
+                ("", "", "center")
+            }
+            "aligned" | "align" | "align*" | "split" => ("", "", "right left"),
+            "gathered" | "gather" | "gather*" => ("", "", "center"),
+            name => bail!("Unknown environment: {}", name),
+        };
+
+        let mut table = String::new();
+        let mut row = String::new();
+
+        loop {
+            let (cell, end) = self.parse_row(false)?;
+            row.push_str(&format!("<mtd>{}</mtd>", cell));
+
+            match end {
+                End::Cell => continue,
+                End::Row => {
+                    table.push_str(&format!("<mtr>{}</mtr>", std::mem::take(&mut row)));
+                }
+                End::Env(end) if end == name => {
+                    if row != "<mtd><mrow></mrow></mtd>" {
+                        table.push_str(&format!("<mtr>{}</mtr>", row));
+                    }
+                    break;
+                }
+                End::Eof => bail!("`\\begin{{{}}}` without `\\end{{{}}}`", name, name),
+                end => return Err(self.unexpected(end)),
+            }
+        }
+
+        let table = format!(
+            r#"<mtable columnalign="{}" displaystyle="{}">{}</mtable>"#,
+            align,
+            align == "right left",
+            table
+        );
+
+        Ok(match (open, close) {
+            ("", "") => table,
+            (open, close) => {
+                let close = match close {
+                    "" => String::new(),
+                    close => format!(r#"<mo fence="true">{}</mo>"#, close),
+                };
+                format!(
+                    r#"<mrow><mo fence="true">{}</mo>{}{}</mrow>"#,
+                    escape(open),
+                    table,
+                    close
+                )
+            }
+        })
+    }
+
+    fn parse_delimiter(&mut self) -> Result<String> {
+        self.skip_whitespace();
+
+        let delimiter = match self.next() {
+            Some('.') => "",
+            Some('(') => "(",
+            Some(')') => ")",
+            Some('[') => "[",
+            Some(']') => "]",
+            Some('|') => "|",
+            Some('/') => "/",
+            Some('<') => "\u{27e8}",
+            Some('>') => "\u{27e9}",
+            Some('\\') => match self.read_command().as_str() {
+                "{" | "lbrace" => "{",
+                "}" | "rbrace" => "}",
+                "|" | "Vert" => "\u{2016}",
+                "vert" => "|",
+                "langle" => "\u{27e8}",
+                "rangle" => "\u{27e9}",
+                "lfloor" => "\u{230a}",
+                "rfloor" => "\u{230b}",
+                "lceil" => "\u{2308}",
+                "rceil" => "\u{2309}",
+                "backslash" => "\\",
+                name => bail!("Unknown delimiter: \\{}", name),
+            },
+            Some(c) => bail!("Unknown delimiter: {}", c),
+            None => bail!("Missing delimiter"),
+        };
+
+        Ok(delimiter.to_owned())
+    }
+
+    fn parse_text_arg(&mut self) -> Result<String> {
+        self.skip_whitespace();
+
+        if self.next() != Some('{') {
+            bail!("Missing `{{`");
+        }
+
+        let mut text = String::new();
+        let mut depth = 0;
+
+        loop {
+            match self.next() {
+                Some('{') => depth += 1,
+                Some('}') if depth == 0 => return Ok(text),
+                Some('}') => depth -= 1,
+                Some('\\') => match self.next() {
+                    Some(c) => {
+                        text.push(c);
+                        continue;
+                    }
+                    None => bail!("Missing `}}`"),
+                },
+                Some(c) => {
+                    text.push(c);
+                    continue;
+                }
+                None => bail!("Missing `}}`"),
+            }
+            text.push(self.chars[self.pos - 1]);
+        }
+    }
+
+    fn identifier(&self, c: char) -> String {
+        match self.variant {
+            Some(Variant::Normal) => format!(r#"<mi mathvariant="normal">{}</mi>"#, c),
+            _ => format!("<mi>{}</mi>", self.map_variant(c)),
+        }
+    }
+
+    fn map_variant(&self, c: char) -> char {
+        let (upper, lower, digit) = match self.variant {
+            None | Some(Variant::Normal) => return c,
+            Some(Variant::Bold) => (0x1d400, 0x1d41a, Some(0x1d7ce)),
+            Some(Variant::Italic) => (0x1d434, 0x1d44e, None),
+            Some(Variant::BoldItalic) => (0x1d468, 0x1d482, Some(0x1d7ce)),
+            Some(Variant::Script) => (0x1d49c, 0x1d4b6, None),
+            Some(Variant::Fraktur) => (0x1d504, 0x1d51e, None),
+            Some(Variant::DoubleStruck) => (0x1d538, 0x1d552, Some(0x1d7d8)),
+            Some(Variant::SansSerif) => (0x1d5a0, 0x1d5ba, Some(0x1d7e2)),
+            Some(Variant::Monospace) => (0x1d670, 0x1d68a, Some(0x1d7f6)),
+        };
+
+        let exception = match (self.variant, c) {
+            (Some(Variant::Italic), 'h') => Some('\u{210e}'),
+            (Some(Variant::Script), 'B') => Some('\u{212c}'),
+            (Some(Variant::Script), 'E') => Some('\u{2130}'),
+            (Some(Variant::Script), 'F') => Some('\u{2131}'),
+            (Some(Variant::Script), 'H') => Some('\u{210b}'),
+            (Some(Variant::Script), 'I') => Some('\u{2110}'),
+            (Some(Variant::Script), 'L') => Some('\u{2112}'),
+            (Some(Variant::Script), 'M') => Some('\u{2133}'),
+            (Some(Variant::Script), 'R') => Some('\u{211b}'),
+            (Some(Variant::Script), 'e') => Some('\u{212f}'),
+            (Some(Variant::Script), 'g') => Some('\u{210a}'),
+            (Some(Variant::Script), 'o') => Some('\u{2134}'),
+            (Some(Variant::Fraktur), 'C') => Some('\u{212d}'),
+            (Some(Variant::Fraktur), 'H') => Some('\u{210c}'),
+            (Some(Variant::Fraktur), 'I') => Some('\u{2111}'),
+            (Some(Variant::Fraktur), 'R') => Some('\u{211c}'),
+            (Some(Variant::Fraktur), 'Z') => Some('\u{2128}'),
+            (Some(Variant::DoubleStruck), 'C') => Some('\u{2102}'),
+            (Some(Variant::DoubleStruck), 'H') => Some('\u{210d}'),
+            (Some(Variant::DoubleStruck), 'N') => Some('\u{2115}'),
+            (Some(Variant::DoubleStruck), 'P') => Some('\u{2119}'),
+            (Some(Variant::DoubleStruck), 'Q') => Some('\u{211a}'),
+            (Some(Variant::DoubleStruck), 'R') => Some('\u{211d}'),
+            (Some(Variant::DoubleStruck), 'Z') => Some('\u{2124}'),
+            _ => None,
+        };
+
+        let code = match c {
+            'A'..='Z' => upper + (c as u32 - 'A' as u32),
+            'a'..='z' => lower + (c as u32 - 'a' as u32),
+            '0'..='9' => match digit {
+                Some(digit) => digit + (c as u32 - '0' as u32),
+                None => return c,
+            },
+            _ => return c,
+        };
+
+        exception.or_else(|| char::from_u32(code)).unwrap_or(c)
+    }
+
+    fn read_command(&mut self) -> String {
+        let name = self.peek_command();
+        self.pos += name.chars().count();
+        name
+    }
+
+    fn peek_command(&self) -> String {
+        let start = match self.chars.get(self.pos) {
+            Some('\\') => self.pos + 1,
+            _ => self.pos,
+        };
+
+        let name = self.chars[start..]
+            .iter()
+            .take_while(|c| c.is_ascii_alphabetic())
+            .collect::<String>();
+
+        match (name.is_empty(), self.chars.get(start)) {
+            (true, Some(c)) => c.to_string(),
+            (_, _) => name,
+        }
+    }
+
+    fn unexpected(&self, end: End) -> anyhow::Error {
+        match end {
+            End::Eof => anyhow!("Unexpected end"),
+            End::Brace => anyhow!("Unexpected `}}`"),
+            End::Bracket => anyhow!("Unexpected `]`"),
+            End::Right => anyhow!("`\\right` without `\\left`"),
+            End::Cell => anyhow!("`&` outside of an environment"),
+            End::Row => anyhow!("`\\\\` outside of an environment"),
+            End::Env(name) => anyhow!("`\\end{{{}}}` without `\\begin{{{}}}`", name, name),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+}
+
+fn space(width: &str) -> String {
+    format!(r#"<mspace width="{}"/>"#, width)
+}
+
+fn symbol(name: &str) -> Option<(&'static str, &'static str)> {
+    let symbol = match name {
+        "alpha" => ("mi", "\u{3b1}"),
+        "beta" => ("mi", "\u{3b2}"),
+        "gamma" => ("mi", "\u{3b3}"),
+        "delta" => ("mi", "\u{3b4}"),
+        "epsilon" => ("mi", "\u{3f5}"),
+        "varepsilon" => ("mi", "\u{3b5}"),
+        "zeta" => ("mi", "\u{3b6}"),
+        "eta" => ("mi", "\u{3b7}"),
+        "theta" => ("mi", "\u{3b8}"),
+        "vartheta" => ("mi", "\u{3d1}"),
+        "iota" => ("mi", "\u{3b9}"),
+        "kappa" => ("mi", "\u{3ba}"),
+        "lambda" => ("mi", "\u{3bb}"),
+        "mu" => ("mi", "\u{3bc}"),
+        "nu" => ("mi", "\u{3bd}"),
+        "xi" => ("mi", "\u{3be}"),
+        "pi" => ("mi", "\u{3c0}"),
+        "varpi" => ("mi", "\u{3d6}"),
+        "rho" => ("mi", "\u{3c1}"),
+        "varrho" => ("mi", "\u{3f1}"),
+        "sigma" => ("mi", "\u{3c3}"),
+        "varsigma" => ("mi", "\u{3c2}"),
+        "tau" => ("mi", "\u{3c4}"),
+        "upsilon" => ("mi", "\u{3c5}"),
+        "phi" => ("mi", "\u{3d5}"),
+        "varphi" => ("mi", "\u{3c6}"),
+        "chi" => ("mi", "\u{3c7}"),
+        "psi" => ("mi", "\u{3c8}"),
+        "omega" => ("mi", "\u{3c9}"),
+        "Gamma" => ("mi", "\u{393}"),
+        "Delta" => ("mi", "\u{394}"),
+        "Theta" => ("mi", "\u{398}"),
+        "Lambda" => ("mi", "\u{39b}"),
+        "Xi" => ("mi", "\u{39e}"),
+        "Pi" => ("mi", "\u{3a0}"),
+        "Sigma" => ("mi", "\u{3a3}"),
+        "Upsilon" => ("mi", "\u{3a5}"),
+        "Phi" => ("mi", "\u{3a6}"),
+        "Psi" => ("mi", "\u{3a8}"),
+        "Omega" => ("mi", "\u{3a9}"),
+        "infty" => ("mi", "\u{221e}"),
+        "partial" => ("mi", "\u{2202}"),
+        "nabla" => ("mi", "\u{2207}"),
+        "emptyset" | "varnothing" => ("mi", "\u{2205}"),
+        "ell" => ("mi", "\u{2113}"),
+        "hbar" => ("mi", "\u{210f}"),
+        "Re" => ("mi", "\u{211c}"),
+        "Im" => ("mi", "\u{2111}"),
+        "aleph" => ("mi", "\u{2135}"),
+        "angle" => ("mi", "\u{2220}"),
+        "triangle" => ("mi", "\u{25b3}"),
+        "top" => ("mi", "\u{22a4}"),
+        "bot" => ("mi", "\u{22a5}"),
+        "%" => ("mi", "%"),
+        "$" => ("mi", "$"),
+        "#" => ("mi", "#"),
+        "_" => ("mi", "_"),
+        "+" => ("mo", "+"),
+        "&" => ("mo", "&"),
+        "{" | "lbrace" => ("mo", "{"),
+        "}" | "rbrace" => ("mo", "}"),
+        "|" | "Vert" => ("mo", "\u{2016}"),
+        "vert" | "mid" => ("mo", "|"),
+        "backslash" | "setminus" => ("mo", "\u{2216}"),
+        "times" => ("mo", "\u{d7}"),
+        "cdot" => ("mo", "\u{22c5}"),
+        "div" => ("mo", "\u{f7}"),
+        "pm" => ("mo", "\u{b1}"),
+        "mp" => ("mo", "\u{2213}"),
+        "ast" => ("mo", "\u{2217}"),
+        "star" => ("mo", "\u{22c6}"),
+        "circ" => ("mo", "\u{2218}"),
+        "bullet" => ("mo", "\u{2219}"),
+        "oplus" => ("mo", "\u{2295}"),
+        "ominus" => ("mo", "\u{2296}"),
+        "otimes" => ("mo", "\u{2297}"),
+        "odot" => ("mo", "\u{2299}"),
+        "cup" => ("mo", "\u{222a}"),
+        "cap" => ("mo", "\u{2229}"),
+        "wedge" | "land" => ("mo", "\u{2227}"),
+        "vee" | "lor" => ("mo", "\u{2228}"),
+        "neg" | "lnot" => ("mo", "\u{ac}"),
+        "leq" | "le" => ("mo", "\u{2264}"),
+        "geq" | "ge" => ("mo", "\u{2265}"),
+        "neq" | "ne" => ("mo", "\u{2260}"),
+        "ll" => ("mo", "\u{226a}"),
+        "gg" => ("mo", "\u{226b}"),
+        "approx" => ("mo", "\u{2248}"),
+        "equiv" => ("mo", "\u{2261}"),
+        "cong" => ("mo", "\u{2245}"),
+        "sim" => ("mo", "\u{223c}"),
+        "simeq" => ("mo", "\u{2243}"),
+        "propto" => ("mo", "\u{221d}"),
+        "in" => ("mo", "\u{2208}"),
+        "notin" => ("mo", "\u{2209}"),
+        "ni" => ("mo", "\u{220b}"),
+        "subset" => ("mo", "\u{2282}"),
+        "supset" => ("mo", "\u{2283}"),
+        "subseteq" => ("mo", "\u{2286}"),
+        "supseteq" => ("mo", "\u{2287}"),
+        "forall" => ("mo", "\u{2200}"),
+        "exists" => ("mo", "\u{2203}"),
+        "nexists" => ("mo", "\u{2204}"),
+        "perp" => ("mo", "\u{22a5}"),
+        "parallel" => ("mo", "\u{2225}"),
+        "to" | "rightarrow" => ("mo", "\u{2192}"),
+        "gets" | "leftarrow" => ("mo", "\u{2190}"),
+        "leftrightarrow" => ("mo", "\u{2194}"),
+        "Rightarrow" | "implies" => ("mo", "\u{21d2}"),
+        "Leftarrow" | "impliedby" => ("mo", "\u{21d0}"),
+        "Leftrightarrow" | "iff" => ("mo", "\u{21d4}"),
+        "mapsto" => ("mo", "\u{21a6}"),
+        "uparrow" => ("mo", "\u{2191}"),
+        "downarrow" => ("mo", "\u{2193}"),
+        "longrightarrow" => ("mo", "\u{27f6}"),
+        "longleftarrow" => ("mo", "\u{27f5}"),
+        "langle" => ("mo", "\u{27e8}"),
+        "rangle" => ("mo", "\u{27e9}"),
+        "lfloor" => ("mo", "\u{230a}"),
+        "rfloor" => ("mo", "\u{230b}"),
+        "lceil" => ("mo", "\u{2308}"),
+        "rceil" => ("mo", "\u{2309}"),
+        "ldots" | "dots" => ("mo", "\u{2026}"),
+        "cdots" => ("mo", "\u{22ef}"),
+        "vdots" => ("mo", "\u{22ee}"),
+        "ddots" => ("mo", "\u{22f1}"),
+        "prime" => ("mo", "\u{2032}"),
+        "colon" => ("mo", ":"),
+        "bmod" | "mod" => ("mo", "mod"),
+        _ => return None,
+    };
+
+    Some(symbol)
+}
+
+const FUNCTIONS: [&str; 25] = [
+    "sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh",
+    "coth", "log", "ln", "lg", "exp", "det", "dim", "ker", "deg", "gcd", "arg", "hom", "Pr",
+];
+
+fn large_operator(name: &str) -> Option<&'static str> {
+    let operator = match name {
+        "sum" => "\u{2211}",
+        "prod" => "\u{220f}",
+        "coprod" => "\u{2210}",
+        "bigcup" => "\u{22c3}",
+        "bigcap" => "\u{22c2}",
+        "bigoplus" => "\u{2a01}",
+        "bigotimes" => "\u{2a02}",
+        "bigvee" => "\u{22c1}",
+        "bigwedge" => "\u{22c0}",
+        "lim" => "lim",
+        "liminf" => "lim inf",
+        "limsup" => "lim sup",
+        "max" => "max",
+        "min" => "min",
+        "sup" => "sup",
+        "inf" => "inf",
+        "argmax" => "arg max",
+        "argmin" => "arg min",
+        _ => return None,
+    };
+
+    Some(operator)
+}
+
+fn integral(name: &str) -> Option<&'static str> {
+    let integral = match name {
+        "int" => "\u{222b}",
+        "iint" => "\u{222c}",
+        "iiint" => "\u{222d}",
+        "oint" => "\u{222e}",
+        _ => return None,
+    };
+
+    Some(integral)
+}
+
+fn accent(name: &str) -> Option<&'static str> {
+    let accent = match name {
+        "hat" | "widehat" => "^",
+        "bar" | "overline" => "\u{203e}",
+        "vec" | "overrightarrow" => "\u{2192}",
+        "dot" => "\u{2d9}",
+        "ddot" => "\u{a8}",
+        "tilde" | "widetilde" => "~",
+        "check" => "\u{2c7}",
+        "breve" => "\u{2d8}",
+        "acute" => "\u{b4}",
+        "grave" => "`",
+        "overbrace" => "\u{23de}",
+        "underline" => "_",
+        "underbrace" => "\u{23df}",
+        _ => return None,
+    };
+
+    Some(accent)
+}
+
+fn variant(name: &str) -> Option<Variant> {
+    let variant = match name {
+        "mathrm" => Variant::Normal,
+        "mathbf" => Variant::Bold,
+        "mathit" => Variant::Italic,
+        "boldsymbol" | "bm" => Variant::BoldItalic,
+        "mathcal" | "mathscr" => Variant::Script,
+        "mathfrak" => Variant::Fraktur,
+        "mathbb" => Variant::DoubleStruck,
+        "mathsf" => Variant::SansSerif,
+        "mathtt" => Variant::Monospace,
+        _ => return None,
+    };
+
+    Some(variant)
+}
+
+fn big_size(name: &str) -> Option<&'static str> {
+    let size = match name {
+        "big" | "bigl" | "bigr" | "bigm" => "1.2em",
+        "Big" | "Bigl" | "Bigr" | "Bigm" => "1.8em",
+        "bigg" | "biggl" | "biggr" | "biggm" => "2.4em",
+        "Bigg" | "Biggl" | "Biggr" | "Biggm" => "3em",
+        _ => return None,
+    };
+
+    Some(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mathml(tex: &str) -> String {
+        let html = to_mathml(tex, false);
+        assert!(html.starts_with("<math"), "{}: {}", tex, html);
+        html
+    }
+
+    fn error(tex: &str) -> String {
+        let html = to_mathml(tex, true);
+        assert!(
+            html.starts_with(r#"<span class="math-error" title=""#),
+            "{}: {}",
+            tex,
+            html
+        );
+        html
+    }
+
+    #[test]
+    fn fractions_and_roots() {
+        assert!(mathml(r"\frac{a}{b}")
+            .contains("<mfrac><mrow><mi>a</mi></mrow><mrow><mi>b</mi></mrow></mfrac>"));
+        assert!(mathml(r"\sqrt{x}").contains("<msqrt><mrow><mi>x</mi></mrow></msqrt>"));
+        assert!(mathml(r"\sqrt[3]{x}")
+            .contains("<mroot><mrow><mi>x</mi></mrow><mrow><mn>3</mn></mrow></mroot>"));
+    }
+
+    #[test]
+    fn scripts() {
+        assert!(mathml("x^2").contains("<msup><mi>x</mi><mn>2</mn></msup>"));
+        assert!(mathml("x_i^2").contains("<msubsup><mi>x</mi><mi>i</mi><mn>2</mn></msubsup>"));
+        assert!(mathml(r"\sum_{i=1}^n i").contains("<munderover>"));
+    }
+
+    #[test]
+    fn environments() {
+        let html = mathml(r"\begin{pmatrix} a & b \\ c & d \end{pmatrix}");
+        assert!(html.contains(r#"<mo fence="true">(</mo><mtable"#));
+        assert_eq!(html.matches("<mtr>").count(), 2);
+        assert_eq!(html.matches("<mtd>").count(), 4);
+    }
+
+    #[test]
+    fn negated_relations() {
+        assert!(mathml(r"a \not= b").contains("<mo>=\u{338}</mo>"));
+        assert!(mathml(r"a \not = b").contains("<mo>=\u{338}</mo>"));
+        assert!(error(r"\not x").contains("relation"));
+    }
+
+    #[test]
+    fn source_is_escaped() {
+        assert!(mathml("a < b")
+            .contains(r#"<annotation encoding="application/x-tex">a &lt; b</annotation>"#));
+        assert!(error(r"\nope <").contains("$$\\nope &lt;$$</span>"));
+    }
+
+    #[test]
+    fn invalid_math() {
+        for tex in [
+            r"\frac{a}",
+            r"\frac{a",
+            "x^",
+            "a}",
+            r"\nope",
+            r"\left( x",
+            r"\not",
+            r"x \not",
+            r"\begin{matrix} a \end{pmatrix}",
+        ] {
+            error(tex);
+        }
+    }
+}
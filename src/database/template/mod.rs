@@ -1,13 +1,37 @@
-use self::{data_map::*, parameter::*};
+use self::{
+    data_map::*,
+    error::{TemplateError, TemplateErrors},
+    filter::{Escape, Filter},
+    parameter::*,
+    parser::Parser,
+};
+use crate::database::{FacetKind, Page, Pages, Post, Posts};
 use anyhow::{anyhow, Result};
+use chrono::DateTime;
+use chrono_tz::Tz;
 use regex::Regex;
-use std::{borrow::Cow, path::Path};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    ffi::OsStr,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
 use tokio::fs;
 
 pub mod data_map;
-mod markdown;
+pub mod date;
+mod error;
+mod filter;
+pub mod format;
+mod highlight;
+mod html;
+pub mod markdown;
+mod math;
 mod parameter;
+mod parser;
 mod render;
+mod snippet;
 
 #[derive(Clone, Debug)]
 pub struct Template {
@@ -18,139 +42,308 @@ pub struct Template {
     post: Vec<Part>,
     summary: Vec<Part>,
     not_found: Vec<Part>,
+    lists: HashMap<ListKind, ListTemplate>,
+    layouts: HashMap<String, Layout>,
+    shortcodes: HashMap<String, String>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct ListTemplate {
+    summary: Option<Vec<Part>>,
+    page_nav: Option<Vec<Part>>,
+}
+
+#[derive(Clone, Debug)]
+struct Layout {
+    post: Result<Vec<Part>, TemplateErrors>,
+    page: Result<Vec<Part>, TemplateErrors>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ListKind {
+    Index,
+    Archive,
+    Author,
+    Search,
+}
+
+impl ListKind {
+    const ALL: [Self; 4] = [Self::Index, Self::Archive, Self::Author, Self::Search];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Index => "index",
+            Self::Archive => "archive",
+            Self::Author => "author",
+            Self::Search => "search",
+        }
+    }
 }
 
 impl Template {
-    pub async fn from_directory(path: &Path) -> Result<Self> {
-        let param_pattern = Regex::new(r"\{:[a-z._]+\}").unwrap();
+    pub async fn from_directories(paths: &[PathBuf]) -> Result<Self> {
+        let param_pattern = Regex::new(r"\{:[^{}]*\}").unwrap();
+        let partials = Self::read_html_files(paths, Path::new("partials")).await?;
+        let mut errors = TemplateErrors::default();
 
-        let header = fs::read_to_string(path.join("header.html")).await?;
-        let header = Self::parse_string(&header, &param_pattern, |str| match str {
+        let header = Self::read_file(paths, "header.html").await?;
+        let header = Parser::new(&param_pattern, &partials, |str| match str {
             "{:site.url}" => Ok(Part::Site(SiteParameter::Url)),
             "{:site.name}" => Ok(Part::Site(SiteParameter::Name)),
             "{:site.description}" => Ok(Part::Site(SiteParameter::Description)),
             "{:site.page_list}" => Ok(Part::Site(SiteParameter::PageList)),
             "{:site.recent_posts}" => Ok(Part::Site(SiteParameter::RecentPosts)),
+            "{:site.highlight_css}" => Ok(Part::Site(SiteParameter::HighlightCss)),
             "{:document.title}" => Ok(Part::Document(DocumentParameter::Title)),
             "{:document.url}" => Ok(Part::Document(DocumentParameter::Url)),
             "{:document.breadcrumbs}" => Ok(Part::Document(DocumentParameter::Breadcrumb)),
+            "{:document.errors}" => Ok(Part::Document(DocumentParameter::Errors)),
             "{:document.current_page_num_in_list}" => {
                 Ok(Part::Document(DocumentParameter::CurrentPageNumInList))
             }
             "{:document.total_num_of_articles_in_list}" => {
                 Ok(Part::Document(DocumentParameter::TotalNumOfArticleInList))
             }
-            _ => Err(anyhow!("Unknown parameter: {}", str)),
-        })?;
+            _ => Self::parse_dynamic_param(str),
+        })
+        .parse("header.html", &header);
+        let header = Self::collect_errors(&mut errors, header);
 
-        let footer = fs::read_to_string(path.join("footer.html")).await?;
-        let footer = Self::parse_string(&footer, &param_pattern, |str| match str {
+        let footer = Self::read_file(paths, "footer.html").await?;
+        let footer = Parser::new(&param_pattern, &partials, |str| match str {
             "{:site.url}" => Ok(Part::Site(SiteParameter::Url)),
             "{:site.name}" => Ok(Part::Site(SiteParameter::Name)),
             "{:site.description}" => Ok(Part::Site(SiteParameter::Description)),
             "{:site.page_list}" => Ok(Part::Site(SiteParameter::PageList)),
             "{:site.recent_posts}" => Ok(Part::Site(SiteParameter::RecentPosts)),
+            "{:site.highlight_css}" => Ok(Part::Site(SiteParameter::HighlightCss)),
             "{:document.title}" => Ok(Part::Document(DocumentParameter::Title)),
             "{:document.url}" => Ok(Part::Document(DocumentParameter::Url)),
             "{:document.breadcrumbs}" => Ok(Part::Document(DocumentParameter::Breadcrumb)),
+            "{:document.errors}" => Ok(Part::Document(DocumentParameter::Errors)),
             "{:document.current_page_num_in_list}" => {
                 Ok(Part::Document(DocumentParameter::CurrentPageNumInList))
             }
             "{:document.total_num_of_articles_in_list}" => {
                 Ok(Part::Document(DocumentParameter::TotalNumOfArticleInList))
             }
-            _ => Err(anyhow!("Unknown parameter: {}", str)),
-        })?;
+            _ => Self::parse_dynamic_param(str),
+        })
+        .parse("footer.html", &footer);
+        let footer = Self::collect_errors(&mut errors, footer);
 
-        let page_nav = fs::read_to_string(path.join("page_nav.html")).await?;
-        let page_nav = Self::parse_string(&page_nav, &param_pattern, |str| match str {
+        let page_nav_matcher = |str: &str| match str {
             "{:site.url}" => Ok(Part::Site(SiteParameter::Url)),
             "{:site.name}" => Ok(Part::Site(SiteParameter::Name)),
             "{:site.description}" => Ok(Part::Site(SiteParameter::Description)),
             "{:site.page_list}" => Ok(Part::Site(SiteParameter::PageList)),
             "{:site.recent_posts}" => Ok(Part::Site(SiteParameter::RecentPosts)),
+            "{:site.highlight_css}" => Ok(Part::Site(SiteParameter::HighlightCss)),
             "{:document.title}" => Ok(Part::Document(DocumentParameter::Title)),
             "{:document.url}" => Ok(Part::Document(DocumentParameter::Url)),
             "{:document.breadcrumbs}" => Ok(Part::Document(DocumentParameter::Breadcrumb)),
+            "{:document.errors}" => Ok(Part::Document(DocumentParameter::Errors)),
             "{:document.page_nav}" => Ok(Part::Document(DocumentParameter::PageNav)),
+            "{:document.prev_page}" => Ok(Part::Document(DocumentParameter::PrevPage)),
+            "{:document.next_page}" => Ok(Part::Document(DocumentParameter::NextPage)),
             "{:document.current_page_num_in_list}" => {
                 Ok(Part::Document(DocumentParameter::CurrentPageNumInList))
             }
             "{:document.total_num_of_articles_in_list}" => {
                 Ok(Part::Document(DocumentParameter::TotalNumOfArticleInList))
             }
-            _ => Err(anyhow!("Unknown parameter: {}", str)),
-        })?;
+            _ => Self::parse_dynamic_param(str),
+        };
+        let page_nav = Self::read_file(paths, "page_nav.html").await?;
+        let page_nav = Parser::new(&param_pattern, &partials, &page_nav_matcher)
+            .parse("page_nav.html", &page_nav);
+        let page_nav = Self::collect_errors(&mut errors, page_nav);
 
-        let page = fs::read_to_string(path.join("page.html")).await?;
-        let page = Self::parse_string(&page, &param_pattern, |str| match str {
+        let page_matcher = |str: &str| match str {
             "{:site.url}" => Ok(Part::Site(SiteParameter::Url)),
             "{:site.name}" => Ok(Part::Site(SiteParameter::Name)),
             "{:site.description}" => Ok(Part::Site(SiteParameter::Description)),
             "{:site.page_list}" => Ok(Part::Site(SiteParameter::PageList)),
             "{:site.recent_posts}" => Ok(Part::Site(SiteParameter::RecentPosts)),
+            "{:site.highlight_css}" => Ok(Part::Site(SiteParameter::HighlightCss)),
             "{:document.title}" => Ok(Part::Document(DocumentParameter::Title)),
             "{:document.url}" => Ok(Part::Document(DocumentParameter::Url)),
             "{:document.breadcrumbs}" => Ok(Part::Document(DocumentParameter::Breadcrumb)),
+            "{:document.errors}" => Ok(Part::Document(DocumentParameter::Errors)),
             "{:page.title}" => Ok(Part::Page(PageParameter::Title)),
             "{:page.link}" => Ok(Part::Page(PageParameter::Url)),
             "{:page.content}" => Ok(Part::Page(PageParameter::Content)),
+            "{:page.toc}" => Ok(Part::Page(PageParameter::Toc)),
             "{:page.author}" => Ok(Part::Page(PageParameter::Author)),
+            "{:page.word_count}" => Ok(Part::Page(PageParameter::WordCount)),
+            "{:page.reading_time}" => Ok(Part::Page(PageParameter::ReadingTime)),
             "{:page.create_time}" => Ok(Part::Page(PageParameter::CreateTime)),
             "{:page.modify_time}" => Ok(Part::Page(PageParameter::ModifyTime)),
-            _ => Err(anyhow!("Unknown parameter: {}", str)),
-        })?;
+            _ => match Self::parse_params_key(str, "page") {
+                Some(key) => Ok(Part::Page(PageParameter::Param(key))),
+                None => Self::parse_dynamic_param(str),
+            },
+        };
+        let page = Self::read_file(paths, "page.html").await?;
+        let page = Parser::new(&param_pattern, &partials, &page_matcher).parse("page.html", &page);
+        let page = Self::collect_errors(&mut errors, page);
 
-        let post = fs::read_to_string(path.join("post.html")).await?;
-        let post = Self::parse_string(&post, &param_pattern, |str| match str {
+        let post_matcher = |str: &str| match str {
             "{:site.url}" => Ok(Part::Site(SiteParameter::Url)),
             "{:site.name}" => Ok(Part::Site(SiteParameter::Name)),
             "{:site.description}" => Ok(Part::Site(SiteParameter::Description)),
             "{:site.page_list}" => Ok(Part::Site(SiteParameter::PageList)),
             "{:site.recent_posts}" => Ok(Part::Site(SiteParameter::RecentPosts)),
+            "{:site.highlight_css}" => Ok(Part::Site(SiteParameter::HighlightCss)),
             "{:document.title}" => Ok(Part::Document(DocumentParameter::Title)),
             "{:document.url}" => Ok(Part::Document(DocumentParameter::Url)),
             "{:document.breadcrumbs}" => Ok(Part::Document(DocumentParameter::Breadcrumb)),
+            "{:document.errors}" => Ok(Part::Document(DocumentParameter::Errors)),
             "{:post.title}" => Ok(Part::Post(PostParameter::Title)),
             "{:post.link}" => Ok(Part::Post(PostParameter::Url)),
             "{:post.content}" => Ok(Part::Post(PostParameter::Content)),
+            "{:post.toc}" => Ok(Part::Post(PostParameter::Toc)),
             "{:post.author}" => Ok(Part::Post(PostParameter::Author)),
+            "{:post.word_count}" => Ok(Part::Post(PostParameter::WordCount)),
+            "{:post.reading_time}" => Ok(Part::Post(PostParameter::ReadingTime)),
             "{:post.create_time}" => Ok(Part::Post(PostParameter::CreateTime)),
             "{:post.modify_time}" => Ok(Part::Post(PostParameter::ModifyTime)),
-            _ => Err(anyhow!("Unknown parameter: {}", str)),
-        })?;
+            "{:post.prev}" => Ok(Part::Post(PostParameter::Prev)),
+            "{:post.next}" => Ok(Part::Post(PostParameter::Next)),
+            "{:post.series}" => Ok(Part::Post(PostParameter::Series)),
+            "{:post.series_toc}" => Ok(Part::Post(PostParameter::SeriesToc)),
+            "{:post.series_prev}" => Ok(Part::Post(PostParameter::SeriesPrev)),
+            "{:post.series_next}" => Ok(Part::Post(PostParameter::SeriesNext)),
+            "{:post.related}" => Ok(Part::Post(PostParameter::Related)),
+            "{:post.tags}" => Ok(Part::Post(PostParameter::Tags)),
+            _ => match Self::parse_params_key(str, "post") {
+                Some(key) => Ok(Part::Post(PostParameter::Param(key))),
+                None => Self::parse_dynamic_param(str),
+            },
+        };
+        let post = Self::read_file(paths, "post.html").await?;
+        let post = Parser::new(&param_pattern, &partials, &post_matcher).parse("post.html", &post);
+        let post = Self::collect_errors(&mut errors, post);
 
-        let summary = fs::read_to_string(path.join("summary.html")).await?;
-        let summary = Self::parse_string(&summary, &param_pattern, |str| match str {
+        let summary_matcher = |str: &str| match str {
             "{:site.url}" => Ok(Part::Site(SiteParameter::Url)),
             "{:site.name}" => Ok(Part::Site(SiteParameter::Name)),
             "{:site.description}" => Ok(Part::Site(SiteParameter::Description)),
             "{:site.page_list}" => Ok(Part::Site(SiteParameter::PageList)),
             "{:site.recent_posts}" => Ok(Part::Site(SiteParameter::RecentPosts)),
+            "{:site.highlight_css}" => Ok(Part::Site(SiteParameter::HighlightCss)),
             "{:document.title}" => Ok(Part::Document(DocumentParameter::Title)),
             "{:document.url}" => Ok(Part::Document(DocumentParameter::Url)),
             "{:document.breadcrumbs}" => Ok(Part::Document(DocumentParameter::Breadcrumb)),
+            "{:document.errors}" => Ok(Part::Document(DocumentParameter::Errors)),
+            "{:summary.type}" => Ok(Part::Summary(SummaryParameter::Type)),
             "{:summary.title}" => Ok(Part::Summary(SummaryParameter::Title)),
             "{:summary.link}" => Ok(Part::Summary(SummaryParameter::Url)),
             "{:summary.summary}" => Ok(Part::Summary(SummaryParameter::Summary)),
+            "{:summary.has_more}" => Ok(Part::Summary(SummaryParameter::HasMore)),
             "{:summary.author}" => Ok(Part::Summary(SummaryParameter::Author)),
+            "{:summary.word_count}" => Ok(Part::Summary(SummaryParameter::WordCount)),
+            "{:summary.reading_time}" => Ok(Part::Summary(SummaryParameter::ReadingTime)),
             "{:summary.create_time}" => Ok(Part::Summary(SummaryParameter::CreateTime)),
             "{:summary.modify_time}" => Ok(Part::Summary(SummaryParameter::ModifyTime)),
-            _ => Err(anyhow!("Unknown parameter: {}", str)),
-        })?;
+            "{:summary.snippet}" => Ok(Part::Summary(SummaryParameter::Snippet)),
+            "{:summary.match_count}" => Ok(Part::Summary(SummaryParameter::MatchCount)),
+            "{:summary.tags}" => Ok(Part::Summary(SummaryParameter::Tags)),
+            _ => Self::parse_dynamic_param(str),
+        };
+        let summary = Self::read_file(paths, "summary.html").await?;
+        let summary = Parser::new(&param_pattern, &partials, &summary_matcher)
+            .parse("summary.html", &summary);
+        let summary = Self::collect_errors(&mut errors, summary);
 
-        let not_found = fs::read_to_string(path.join("not_found.html")).await?;
-        let not_found = Self::parse_string(&not_found, &param_pattern, |str| match str {
+        let not_found = Self::read_file(paths, "not_found.html").await?;
+        let not_found = Parser::new(&param_pattern, &partials, |str| match str {
             "{:site.url}" => Ok(Part::Site(SiteParameter::Url)),
             "{:site.name}" => Ok(Part::Site(SiteParameter::Name)),
             "{:site.description}" => Ok(Part::Site(SiteParameter::Description)),
             "{:site.page_list}" => Ok(Part::Site(SiteParameter::PageList)),
             "{:site.recent_posts}" => Ok(Part::Site(SiteParameter::RecentPosts)),
+            "{:site.highlight_css}" => Ok(Part::Site(SiteParameter::HighlightCss)),
             "{:document.title}" => Ok(Part::Document(DocumentParameter::Title)),
             "{:document.url}" => Ok(Part::Document(DocumentParameter::Url)),
             "{:document.breadcrumbs}" => Ok(Part::Document(DocumentParameter::Breadcrumb)),
-            _ => Err(anyhow!("Unknown parameter: {}", str)),
-        })?;
+            "{:document.errors}" => Ok(Part::Document(DocumentParameter::Errors)),
+            _ => Self::parse_dynamic_param(str),
+        })
+        .parse("not_found.html", &not_found);
+        let not_found = Self::collect_errors(&mut errors, not_found);
+
+        let mut lists = HashMap::new();
+        for kind in ListKind::ALL {
+            let files =
+                Self::read_html_files(paths, &Path::new("lists").join(kind.as_str())).await?;
+            let file_name = |name: &str| format!("lists/{}/{}", kind.as_str(), name);
+
+            let list = ListTemplate {
+                summary: files.get("summary.html").map(|summary| {
+                    let summary = Parser::new(&param_pattern, &partials, &summary_matcher)
+                        .parse(&file_name("summary.html"), summary);
+                    Self::collect_errors(&mut errors, summary)
+                }),
+                page_nav: files.get("page_nav.html").map(|page_nav| {
+                    let page_nav = Parser::new(&param_pattern, &partials, &page_nav_matcher)
+                        .parse(&file_name("page_nav.html"), page_nav);
+                    Self::collect_errors(&mut errors, page_nav)
+                }),
+            };
+            lists.insert(kind, list);
+        }
+
+        let mut layouts = HashMap::new();
+        for (name, layout) in Self::read_html_files(paths, Path::new("layouts")).await? {
+            let file_name = format!("layouts/{}", name);
+            let layout = Layout {
+                post: Parser::new(&param_pattern, &partials, &post_matcher)
+                    .parse(&file_name, &layout),
+                page: Parser::new(&param_pattern, &partials, &page_matcher)
+                    .parse(&file_name, &layout),
+            };
+
+            // A layout only needs to work for posts or pages, so report the errors of the closer one
+            if let (Err(post_errors), Err(page_errors)) = (&layout.post, &layout.page) {
+                if post_errors.len() <= page_errors.len() {
+                    errors.extend(post_errors.clone());
+                } else {
+                    errors.extend(page_errors.clone());
+                }
+                continue;
+            }
+
+            layouts.insert(name.trim_end_matches(".html").to_owned(), layout);
+        }
+
+        let mut shortcodes = HashMap::new();
+
+        for (name, shortcode) in Self::read_html_files(paths, Path::new("shortcodes")).await? {
+            let file_name = format!("shortcodes/{}", name);
+
+            for cap in param_pattern.find_iter(&shortcode) {
+                let tag = &cap.as_str()[2..cap.as_str().len() - 1];
+                let is_valid = tag.strip_prefix("shortcode.").is_some_and(|arg| {
+                    !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                });
+
+                if !is_valid {
+                    errors.push(TemplateError::new(
+                        &file_name,
+                        &shortcode,
+                        cap.start(),
+                        format!("Unknown parameter: {}, shortcodes can only use `{{:shortcode.ARG}}` and `{{:shortcode.body}}`", cap.as_str()),
+                    ));
+                }
+            }
+
+            shortcodes.insert(name.trim_end_matches(".html").to_owned(), shortcode);
+        }
+
+        if !errors.is_empty() {
+            return Err(anyhow!("Failed to parse the template:\n{}", errors));
+        }
 
         Ok(Self {
             header,
@@ -160,129 +353,326 @@ impl Template {
             post,
             summary,
             not_found,
+            lists,
+            layouts,
+            shortcodes,
+        })
+    }
+
+    pub fn shortcodes(&self) -> &HashMap<String, String> {
+        &self.shortcodes
+    }
+
+    pub fn check_layouts(&self, posts: &Posts, pages: &Pages) -> Result<()> {
+        for post in &posts.data {
+            if let Some(name) = &post.layout {
+                self.get_layout(name)?.post.as_ref().map_err(|err| {
+                    anyhow!(
+                        "Layout `{}` of post `{}` can't be used for posts:\n{}",
+                        name,
+                        post.title,
+                        err
+                    )
+                })?;
+            }
+        }
+
+        for page in &pages.data {
+            if let Some(name) = &page.layout {
+                self.get_layout(name)?.page.as_ref().map_err(|err| {
+                    anyhow!(
+                        "Layout `{}` of page `{}` can't be used for pages:\n{}",
+                        name,
+                        page.title,
+                        err
+                    )
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_layout(&self, name: &str) -> Result<&Layout> {
+        self.layouts
+            .get(name)
+            .ok_or_else(|| anyhow!("Unknown layout: {}", name))
+    }
+
+    async fn read_file(paths: &[PathBuf], name: &str) -> Result<String> {
+        for path in paths {
+            match fs::read_to_string(path.join(name)).await {
+                Ok(file) => return Ok(file),
+                Err(err) if err.kind() == ErrorKind::NotFound => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Err(anyhow!("Template file not found: {}", name))
+    }
+
+    async fn read_html_files(paths: &[PathBuf], dir: &Path) -> Result<HashMap<String, String>> {
+        let mut files = HashMap::new();
+
+        for path in paths.iter().rev() {
+            let mut dir = match fs::read_dir(path.join(dir)).await {
+                Ok(dir) => dir,
+                Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            };
+
+            while let Some(entry) = dir.next_entry().await? {
+                let path = entry.path();
+
+                if path.extension() == Some(OsStr::new("html")) {
+                    let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                    files.insert(name, fs::read_to_string(&path).await?);
+                }
+            }
+        }
+
+        Ok(files)
+    }
+
+    fn collect_errors(
+        errors: &mut TemplateErrors,
+        result: Result<Vec<Part>, TemplateErrors>,
+    ) -> Vec<Part> {
+        result.unwrap_or_else(|err| {
+            errors.extend(err);
+            Vec::new()
         })
     }
 
-    fn parse_string<M>(str: &str, param_pattern: &Regex, param_matcher: M) -> Result<Vec<Part>>
-    where
-        M: Fn(&str) -> Result<Part>,
-    {
-        let mut result = Vec::new();
-        let mut start = 0;
+    fn parse_params_key(str: &str, scope: &str) -> Option<String> {
+        str.strip_prefix("{:")
+            .and_then(|str| str.strip_prefix(scope))
+            .and_then(|str| str.strip_prefix(".params."))
+            .and_then(|key| key.strip_suffix('}'))
+            .filter(|key| !key.is_empty())
+            .map(|key| key.to_owned())
+    }
 
-        for cap in param_pattern.find_iter(str) {
-            result.push(Part::Static(str[start..cap.start()].to_owned()));
-            start = cap.end();
+    fn parse_dynamic_param(str: &str) -> Result<Part> {
+        if let Some(key) = Self::parse_params_key(str, "site") {
+            return Ok(Part::Site(SiteParameter::Param(key)));
+        }
 
-            let param = param_matcher(cap.as_str())?;
-            result.push(param);
+        if let Some(name) = str
+            .strip_prefix("{:site.menu.")
+            .and_then(|name| name.strip_suffix('}'))
+        {
+            return Ok(Part::Site(SiteParameter::Menu(name.to_owned())));
         }
 
-        result.push(Part::Static(str[start..].to_owned()));
+        if let Some(kind) = str
+            .strip_prefix("{:document.facets.")
+            .and_then(|name| name.strip_suffix('}'))
+            .and_then(FacetKind::parse)
+        {
+            return Ok(Part::Document(DocumentParameter::Facet(kind)));
+        }
 
-        Ok(result)
+        Err(anyhow!("Unknown parameter: {}", str))
     }
 
     fn header(&self, site_data: &SiteDataMap, document_data: &DocumentDataMap) -> String {
-        self.header
-            .iter()
-            .map(|part| match part {
-                Part::Static(str) => Cow::Borrowed(str.as_str()),
-                Part::Site(param) => site_data.get(param),
-                Part::Document(param) => document_data.get(param),
-                _ => unreachable!(),
-            })
-            .collect()
+        Scope::new(site_data, document_data).render(&self.header)
     }
 
     fn footer(&self, site_data: &SiteDataMap, document_data: &DocumentDataMap) -> String {
-        self.footer
-            .iter()
-            .map(|part| match part {
-                Part::Static(str) => Cow::Borrowed(str.as_str()),
-                Part::Site(param) => site_data.get(param),
-                Part::Document(param) => document_data.get(param),
-                _ => unreachable!(),
-            })
-            .collect()
+        Scope::new(site_data, document_data).render(&self.footer)
     }
 
-    fn page_nav(&self, site_data: &SiteDataMap, document_data: &DocumentDataMap) -> String {
-        self.page_nav
-            .iter()
-            .map(|part| match part {
-                Part::Static(str) => Cow::Borrowed(str.as_str()),
-                Part::Site(param) => site_data.get(param),
-                Part::Document(param) => document_data.get(param),
-                _ => unreachable!(),
-            })
-            .collect()
+    fn page_nav(
+        &self,
+        list: ListKind,
+        site_data: &SiteDataMap,
+        document_data: &DocumentDataMap,
+    ) -> String {
+        let page_nav = self
+            .lists
+            .get(&list)
+            .and_then(|list| list.page_nav.as_ref())
+            .unwrap_or(&self.page_nav);
+
+        Scope::new(site_data, document_data).render(page_nav)
     }
 
     fn page(
         &self,
+        page: &Page,
         site_data: &SiteDataMap,
         document_data: &DocumentDataMap,
         page_data: &PageDataMap,
     ) -> String {
-        self.page
-            .iter()
-            .map(|part| match part {
-                Part::Static(str) => Cow::Borrowed(str.as_str()),
-                Part::Site(param) => site_data.get(param),
-                Part::Document(param) => document_data.get(param),
-                Part::Page(param) => page_data.get(param),
-                _ => unreachable!(),
-            })
-            .collect()
+        let layout = page
+            .layout
+            .as_ref()
+            .or(page.directory.as_ref())
+            .and_then(|name| self.layouts.get(name))
+            .and_then(|layout| layout.page.as_ref().ok())
+            .unwrap_or(&self.page);
+
+        Scope {
+            page: Some(page_data),
+            ..Scope::new(site_data, document_data)
+        }
+        .render(layout)
     }
 
     fn post(
         &self,
+        post: &Post,
         site_data: &SiteDataMap,
         document_data: &DocumentDataMap,
         post_data: &PostDataMap,
     ) -> String {
-        self.post
-            .iter()
-            .map(|part| match part {
-                Part::Static(str) => Cow::Borrowed(str.as_str()),
-                Part::Site(param) => site_data.get(param),
-                Part::Document(param) => document_data.get(param),
-                Part::Post(param) => post_data.get(param),
-                _ => unreachable!(),
-            })
-            .collect()
+        let layout = post
+            .layout
+            .as_ref()
+            .or(post.directory.as_ref())
+            .and_then(|name| self.layouts.get(name))
+            .and_then(|layout| layout.post.as_ref().ok())
+            .unwrap_or(&self.post);
+
+        Scope {
+            post: Some(post_data),
+            ..Scope::new(site_data, document_data)
+        }
+        .render(layout)
     }
 
     fn summary(
         &self,
+        list: ListKind,
         site_data: &SiteDataMap,
         document_data: &DocumentDataMap,
         summary_data: &SummaryDataMap,
     ) -> String {
-        self.summary
-            .iter()
-            .map(|part| match part {
-                Part::Static(str) => Cow::Borrowed(str.as_str()),
-                Part::Site(param) => site_data.get(param),
-                Part::Document(param) => document_data.get(param),
-                Part::Summary(param) => summary_data.get(param),
-                _ => unreachable!(),
-            })
-            .collect()
+        let summary = self
+            .lists
+            .get(&list)
+            .and_then(|list| list.summary.as_ref())
+            .unwrap_or(&self.summary);
+
+        Scope {
+            summary: Some(summary_data),
+            ..Scope::new(site_data, document_data)
+        }
+        .render(summary)
     }
 
     fn not_found(&self, site_data: &SiteDataMap, document_data: &DocumentDataMap) -> String {
-        self.not_found
-            .iter()
-            .map(|part| match part {
-                Part::Static(str) => Cow::Borrowed(str.as_str()),
-                Part::Site(param) => site_data.get(param),
-                Part::Document(param) => document_data.get(param),
-                _ => unreachable!(),
-            })
-            .collect()
+        Scope::new(site_data, document_data).render(&self.not_found)
+    }
+}
+
+struct Scope<'s> {
+    site: &'s SiteDataMap<'s>,
+    document: &'s DocumentDataMap<'s>,
+    page: Option<&'s PageDataMap<'s>>,
+    post: Option<&'s PostDataMap<'s>>,
+    summary: Option<&'s SummaryDataMap<'s>>,
+}
+
+impl<'s> Scope<'s> {
+    fn new(site: &'s SiteDataMap<'s>, document: &'s DocumentDataMap<'s>) -> Self {
+        Self {
+            site,
+            document,
+            page: None,
+            post: None,
+            summary: None,
+        }
+    }
+
+    fn render(&self, parts: &[Part]) -> String {
+        let mut result = String::new();
+        self.render_parts(&mut result, parts, &mut Vec::new());
+        result
+    }
+
+    fn render_parts(&self, result: &mut String, parts: &[Part], items: &mut Vec<ItemDataMap<'s>>) {
+        for part in parts {
+            match part {
+                Part::Static(str) => result.push_str(str),
+                Part::If {
+                    condition,
+                    then,
+                    otherwise,
+                } => {
+                    let is_present = if condition.is_list() {
+                        !self.get_list(condition).is_empty()
+                    } else {
+                        !self.get(condition, items).is_empty()
+                    };
+
+                    if is_present {
+                        self.render_parts(result, then, items);
+                    } else {
+                        self.render_parts(result, otherwise, items);
+                    }
+                }
+                Part::For { list, body } => {
+                    for item in self.get_list(list) {
+                        items.push(item);
+                        self.render_parts(result, body, items);
+                        items.pop();
+                    }
+                }
+                part => result.push_str(&self.get(part, items)),
+            }
+        }
+    }
+
+    fn get<'a>(&'a self, part: &Part, items: &'a [ItemDataMap<'s>]) -> Cow<'a, str> {
+        match part {
+            Part::Site(param) => self.site.get(param),
+            Part::Document(param) => self.document.get(param),
+            Part::Page(param) => self.page.unwrap().get(param),
+            Part::Post(param) => self.post.unwrap().get(param),
+            Part::Summary(param) => self.summary.unwrap().get(param),
+            Part::Item { depth, param } => items[*depth].get(param),
+            Part::Filtered {
+                value,
+                filters,
+                escape,
+            } => {
+                let (value, filters) = match filters.split_first() {
+                    Some((Filter::Date(format), filters)) => {
+                        (Cow::Owned(format.format(self.get_time(value))), filters)
+                    }
+                    _ => (self.get(value, items), filters.as_slice()),
+                };
+
+                escape.apply(
+                    filters
+                        .iter()
+                        .fold(value, |value, filter| filter.apply(value)),
+                )
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn get_time(&self, part: &Part) -> &'s DateTime<Tz> {
+        match part {
+            Part::Page(param) => self.page.unwrap().get_time(param),
+            Part::Post(param) => self.post.unwrap().get_time(param),
+            Part::Summary(param) => self.summary.unwrap().get_time(param),
+            _ => unreachable!(),
+        }
+    }
+
+    fn get_list(&self, part: &Part) -> Vec<ItemDataMap<'s>> {
+        match part {
+            Part::Site(param) => self.site.get_list(param),
+            Part::Document(param) => self.document.get_list(param),
+            Part::Post(param) => self.post.unwrap().get_list(param),
+            Part::Summary(param) => self.summary.unwrap().get_list(param),
+            _ => unreachable!(),
+        }
     }
 }
 
@@ -294,4 +684,112 @@ pub enum Part {
     Page(PageParameter),
     Post(PostParameter),
     Summary(SummaryParameter),
+    Item {
+        depth: usize,
+        param: ItemParameter,
+    },
+    If {
+        condition: Box<Part>,
+        then: Vec<Part>,
+        otherwise: Vec<Part>,
+    },
+    For {
+        list: Box<Part>,
+        body: Vec<Part>,
+    },
+    Filtered {
+        value: Box<Part>,
+        filters: Vec<Filter>,
+        escape: Escape,
+    },
+}
+
+impl Part {
+    fn is_list(&self) -> bool {
+        match self {
+            Self::Site(param) => param.is_list(),
+            Self::Document(param) => param.is_list(),
+            Self::Post(param) => param.is_list(),
+            Self::Summary(param) => param.is_list(),
+            _ => false,
+        }
+    }
+
+    fn is_html(&self) -> bool {
+        match self {
+            Self::Site(param) => param.is_html(),
+            Self::Document(param) => param.is_html(),
+            Self::Page(param) => param.is_html(),
+            Self::Post(param) => param.is_html(),
+            Self::Summary(param) => param.is_html(),
+            _ => false,
+        }
+    }
+
+    fn is_time(&self) -> bool {
+        match self {
+            Self::Page(param) => param.is_time(),
+            Self::Post(param) => param.is_time(),
+            Self::Summary(param) => param.is_time(),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{markdown::Renderer, *};
+    use crate::{
+        database::{Images, Params, Problem},
+        Config,
+    };
+    use hyper::{Body, Request};
+    use std::sync::Mutex;
+
+    pub fn with_renderer<T>(f: impl FnOnce(Renderer<'_>) -> T) -> (T, Vec<Problem>) {
+        Config::init_for_test();
+        let (shortcodes, images, links) = (HashMap::new(), Images::default(), HashMap::new());
+        let problems = Mutex::new(Vec::new());
+        let renderer = Renderer::new(
+            Path::new("/nonexistent"),
+            &shortcodes,
+            &images,
+            &links,
+            &problems,
+        );
+        let result = f(renderer.with_path(Path::new("posts/test.md")));
+
+        (result, problems.into_inner().unwrap())
+    }
+
+    pub fn parse(template: &str, partials: &[(&str, &str)]) -> Result<Vec<Part>, TemplateErrors> {
+        let param_pattern = Regex::new(r"\{:[^{}]*\}").unwrap();
+        let partials = partials
+            .iter()
+            .map(|(name, partial)| (name.to_string(), partial.to_string()))
+            .collect();
+
+        Parser::new(&param_pattern, &partials, |str| match str {
+            "{:site.name}" => Ok(Part::Site(SiteParameter::Name)),
+            "{:document.title}" => Ok(Part::Document(DocumentParameter::Title)),
+            "{:document.url}" => Ok(Part::Document(DocumentParameter::Url)),
+            "{:document.breadcrumbs}" => Ok(Part::Document(DocumentParameter::Breadcrumb)),
+            "{:document.errors}" => Ok(Part::Document(DocumentParameter::Errors)),
+            "{:document.page_nav}" => Ok(Part::Document(DocumentParameter::PageNav)),
+            _ => Template::parse_dynamic_param(str),
+        })
+        .parse("test.html", template)
+    }
+
+    pub fn render(template: &str, document: &DocumentDataMap) -> String {
+        let params = Params::default();
+        let site = SiteDataMap::for_test(&params);
+
+        Scope::new(&site, document).render(&parse(template, &[]).unwrap())
+    }
+
+    pub fn request(uri: &str) -> Request<Body> {
+        Config::init_for_test();
+        Request::get(uri).body(Body::empty()).unwrap()
+    }
 }
@@ -1,3 +1,66 @@
+use crate::database::FacetKind;
+
+pub const PARAMETER_NAMES: [&str; 58] = [
+    "site.url",
+    "site.name",
+    "site.description",
+    "site.page_list",
+    "site.recent_posts",
+    "site.highlight_css",
+    "document.title",
+    "document.url",
+    "document.breadcrumbs",
+    "document.errors",
+    "document.page_nav",
+    "document.prev_page",
+    "document.next_page",
+    "document.current_page_num_in_list",
+    "document.total_num_of_articles_in_list",
+    "document.facets.author",
+    "document.facets.year",
+    "document.facets.month",
+    "document.facets.tag",
+    "page.title",
+    "page.link",
+    "page.content",
+    "page.toc",
+    "page.author",
+    "page.word_count",
+    "page.reading_time",
+    "page.create_time",
+    "page.modify_time",
+    "post.title",
+    "post.link",
+    "post.content",
+    "post.toc",
+    "post.author",
+    "post.word_count",
+    "post.reading_time",
+    "post.create_time",
+    "post.modify_time",
+    "post.prev",
+    "post.next",
+    "post.series",
+    "post.series_toc",
+    "post.series_prev",
+    "post.series_next",
+    "post.related",
+    "post.tags",
+    "summary.type",
+    "summary.title",
+    "summary.link",
+    "summary.summary",
+    "summary.has_more",
+    "summary.author",
+    "summary.word_count",
+    "summary.reading_time",
+    "summary.create_time",
+    "summary.modify_time",
+    "summary.snippet",
+    "summary.match_count",
+    "summary.tags",
+];
+
 #[derive(Clone, Debug)]
 pub enum SiteParameter {
     Url,
@@ -5,6 +68,9 @@ pub enum SiteParameter {
     Description,
     PageList,
     RecentPosts,
+    HighlightCss,
+    Menu(String),
+    Param(String),
 }
 
 #[derive(Clone, Debug)]
@@ -13,8 +79,12 @@ pub enum DocumentParameter {
     Url,
     Breadcrumb,
     PageNav,
+    PrevPage,
+    NextPage,
     CurrentPageNumInList,
     TotalNumOfArticleInList,
+    Errors,
+    Facet(FacetKind),
 }
 
 #[derive(Clone, Debug)]
@@ -22,9 +92,13 @@ pub enum PageParameter {
     Title,
     Url,
     Content,
+    Toc,
     Author,
+    WordCount,
+    ReadingTime,
     CreateTime,
     ModifyTime,
+    Param(String),
 }
 
 #[derive(Clone, Debug)]
@@ -32,17 +106,130 @@ pub enum PostParameter {
     Title,
     Url,
     Content,
+    Toc,
     Author,
+    WordCount,
+    ReadingTime,
     CreateTime,
     ModifyTime,
+    Prev,
+    Next,
+    Series,
+    SeriesToc,
+    SeriesPrev,
+    SeriesNext,
+    Related,
+    Tags,
+    Param(String),
 }
 
 #[derive(Clone, Debug)]
 pub enum SummaryParameter {
+    Type,
     Title,
     Url,
     Summary,
+    HasMore,
     Author,
+    WordCount,
+    ReadingTime,
     CreateTime,
     ModifyTime,
+    Snippet,
+    MatchCount,
+    Tags,
+}
+
+#[derive(Clone, Debug)]
+pub enum ItemParameter {
+    Title,
+    Url,
+    IsCurrent,
+    Count,
+}
+
+impl SiteParameter {
+    pub fn is_list(&self) -> bool {
+        matches!(self, Self::PageList | Self::RecentPosts | Self::Menu(_))
+    }
+
+    pub fn is_html(&self) -> bool {
+        self.is_list() || matches!(self, Self::HighlightCss)
+    }
+}
+
+impl DocumentParameter {
+    pub fn is_list(&self) -> bool {
+        matches!(self, Self::PageNav | Self::Facet(_))
+    }
+
+    pub fn is_html(&self) -> bool {
+        matches!(
+            self,
+            Self::Breadcrumb | Self::PageNav | Self::Errors | Self::Facet(_)
+        )
+    }
+}
+
+impl PageParameter {
+    pub fn is_html(&self) -> bool {
+        matches!(self, Self::Content | Self::Toc)
+    }
+
+    pub fn is_time(&self) -> bool {
+        matches!(self, Self::CreateTime | Self::ModifyTime)
+    }
+}
+
+impl PostParameter {
+    pub fn is_list(&self) -> bool {
+        matches!(self, Self::SeriesToc | Self::Related | Self::Tags)
+    }
+
+    pub fn is_html(&self) -> bool {
+        matches!(
+            self,
+            Self::Content
+                | Self::Toc
+                | Self::Prev
+                | Self::Next
+                | Self::SeriesToc
+                | Self::SeriesPrev
+                | Self::SeriesNext
+                | Self::Related
+                | Self::Tags
+        )
+    }
+
+    pub fn is_time(&self) -> bool {
+        matches!(self, Self::CreateTime | Self::ModifyTime)
+    }
+}
+
+impl SummaryParameter {
+    pub fn is_list(&self) -> bool {
+        matches!(self, Self::Tags)
+    }
+
+    pub fn is_html(&self) -> bool {
+        matches!(self, Self::Summary | Self::Snippet | Self::Tags)
+    }
+
+    pub fn is_time(&self) -> bool {
+        matches!(self, Self::CreateTime | Self::ModifyTime)
+    }
+}
+
+impl ItemParameter {
+    pub const NAMES: [&'static str; 4] = ["title", "link", "is_current", "count"];
+
+    pub fn parse(str: &str) -> Option<Self> {
+        match str {
+            "title" => Some(Self::Title),
+            "link" => Some(Self::Url),
+            "is_current" => Some(Self::IsCurrent),
+            "count" => Some(Self::Count),
+            _ => None,
+        }
+    }
 }
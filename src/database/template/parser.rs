@@ -0,0 +1,714 @@
+use super::{
+    error::{TemplateError, TemplateErrors},
+    filter::{Escape, Filter},
+    parameter::{ItemParameter, PARAMETER_NAMES},
+    Part,
+};
+use anyhow::{anyhow, bail, Error, Result};
+use regex::Regex;
+use std::collections::HashMap;
+
+const SCOPES: [&str; 5] = ["site", "document", "page", "post", "summary"];
+
+pub struct Parser<'p, M> {
+    param_pattern: &'p Regex,
+    partials: &'p HashMap<String, String>,
+    param_matcher: M,
+    loop_vars: Vec<String>,
+    includes: Vec<String>,
+    in_script: bool,
+    file: String,
+    errors: TemplateErrors,
+}
+
+enum Token<'s> {
+    Text(&'s str),
+    Tag(usize, &'s str),
+}
+
+struct Source<'s> {
+    file: &'s str,
+    text: &'s str,
+}
+
+impl<'p, M> Parser<'p, M>
+where
+    M: Fn(&str) -> Result<Part>,
+{
+    pub fn new(
+        param_pattern: &'p Regex,
+        partials: &'p HashMap<String, String>,
+        param_matcher: M,
+    ) -> Self {
+        Self {
+            param_pattern,
+            partials,
+            param_matcher,
+            loop_vars: Vec::new(),
+            includes: Vec::new(),
+            in_script: false,
+            file: String::new(),
+            errors: TemplateErrors::default(),
+        }
+    }
+
+    pub fn parse(mut self, file: &str, str: &str) -> Result<Vec<Part>, TemplateErrors> {
+        self.file = file.to_owned();
+        let parts = self.parse_source(&Source { file, text: str });
+
+        if self.errors.is_empty() {
+            Ok(parts)
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn parse_source(&mut self, src: &Source) -> Vec<Part> {
+        let mut tokens = Vec::new();
+        let mut start = 0;
+
+        for cap in self.param_pattern.find_iter(src.text) {
+            tokens.push(Token::Text(&src.text[start..cap.start()]));
+            tokens.push(Token::Tag(
+                cap.start(),
+                &cap.as_str()[2..cap.as_str().len() - 1],
+            ));
+            start = cap.end();
+        }
+
+        tokens.push(Token::Text(&src.text[start..]));
+
+        let mut tokens = tokens.into_iter();
+        let mut parts = Vec::new();
+
+        loop {
+            let (block, end) = self.parse_block(src, &mut tokens);
+            parts.extend(block);
+
+            match end {
+                Some((offset, tag)) => {
+                    self.error(src, offset, anyhow!("Unexpected `{{:{}}}`", tag))
+                }
+                None => return parts,
+            }
+        }
+    }
+
+    fn parse_block<'s>(
+        &mut self,
+        src: &Source,
+        tokens: &mut impl Iterator<Item = Token<'s>>,
+    ) -> (Vec<Part>, Option<(usize, &'s str)>) {
+        let mut parts = Vec::new();
+
+        while let Some(token) = tokens.next() {
+            let (offset, tag) = match token {
+                Token::Text(text) => {
+                    if !text.is_empty() {
+                        self.update_in_script(text);
+                        parts.push(Part::Static(text.to_owned()));
+                    }
+                    continue;
+                }
+                Token::Tag(offset, tag) => (offset, tag),
+            };
+
+            if let "else" | "endif" | "endfor" = tag {
+                return (parts, Some((offset, tag)));
+            } else if let Some(condition) = tag.strip_prefix("if ") {
+                let condition = self.parse_value(condition.trim(), false);
+
+                let (then, end) = self.parse_block(src, tokens);
+                let (otherwise, end) = match end {
+                    Some((_, "else")) => self.parse_block(src, tokens),
+                    end => (Vec::new(), end),
+                };
+
+                match (condition, end) {
+                    (Ok(condition), Some((_, "endif"))) => parts.push(Part::If {
+                        condition: Box::new(condition),
+                        then,
+                        otherwise,
+                    }),
+                    (Err(err), Some((_, "endif"))) => self.error(src, offset, err),
+                    (condition, end) => {
+                        if let Err(err) = condition {
+                            self.error(src, offset, err);
+                        }
+                        self.error(
+                            src,
+                            offset,
+                            anyhow!("`{{:{}}}` is not closed by `{{:endif}}`", tag),
+                        );
+                        if end.is_some() {
+                            return (parts, end);
+                        }
+                    }
+                }
+            } else if let Some(expr) = tag.strip_prefix("for ") {
+                let (var, list) = match expr.split_once(" in ") {
+                    Some((var, list)) => (var.trim(), list.trim()),
+                    None => {
+                        self.error(
+                            src,
+                            offset,
+                            anyhow!("`{{:{}}}` should be `{{:for ITEM in LIST}}`", tag),
+                        );
+                        ("", "")
+                    }
+                };
+
+                let list_param = if list.is_empty() {
+                    None
+                } else if var.is_empty()
+                    || !var.chars().all(|c| c.is_ascii_lowercase() || c == '_')
+                    || SCOPES.contains(&var)
+                    || self.loop_vars.iter().any(|loop_var| loop_var == var)
+                {
+                    self.error(
+                        src,
+                        offset,
+                        anyhow!(
+                            "`{}` can't be used as the loop item name in `{{:{}}}`",
+                            var,
+                            tag
+                        ),
+                    );
+                    None
+                } else {
+                    match self.parse_param(list) {
+                        Ok(list_param) if list_param.is_list() => Some(list_param),
+                        Ok(_) => {
+                            self.error(
+                                src,
+                                offset,
+                                anyhow!("`{{:{}}}` is not a list in `{{:{}}}`", list, tag),
+                            );
+                            None
+                        }
+                        Err(err) => {
+                            self.error(src, offset, err);
+                            None
+                        }
+                    }
+                };
+
+                self.loop_vars.push(var.to_owned());
+                let (body, end) = self.parse_block(src, tokens);
+                self.loop_vars.pop();
+
+                match (list_param, end) {
+                    (Some(list_param), Some((_, "endfor"))) => parts.push(Part::For {
+                        list: Box::new(list_param),
+                        body,
+                    }),
+                    (None, Some((_, "endfor"))) => {}
+                    (_, end) => {
+                        self.error(
+                            src,
+                            offset,
+                            anyhow!("`{{:{}}}` is not closed by `{{:endfor}}`", tag),
+                        );
+                        if end.is_some() {
+                            return (parts, end);
+                        }
+                    }
+                }
+            } else if let Some(name) = tag.strip_prefix("include ") {
+                let name = name.trim();
+
+                if self.includes.iter().any(|include| include == name) {
+                    self.error(src, offset, anyhow!("Partial `{}` includes itself", name));
+                    continue;
+                }
+
+                let partial = match self.partials.get(name) {
+                    Some(partial) => partial,
+                    None => {
+                        self.error(src, offset, anyhow!("Unknown partial: {}", name));
+                        continue;
+                    }
+                };
+
+                let file = format!("partials/{}", name);
+                self.includes.push(name.to_owned());
+                let included = self.parse_source(&Source {
+                    file: &file,
+                    text: partial,
+                });
+                self.includes.pop();
+
+                parts.extend(included);
+            } else {
+                match self.parse_value(tag, true) {
+                    Ok(part) => parts.push(part),
+                    Err(err) => self.error(src, offset, err),
+                }
+            }
+        }
+
+        (parts, None)
+    }
+
+    fn parse_value(&self, value: &str, is_output: bool) -> Result<Part> {
+        let mut segments = value.split('|');
+        let param = self.parse_param(segments.next().unwrap().trim())?;
+        let filters = segments.map(Filter::parse).collect::<Result<Vec<_>>>()?;
+
+        for (idx, filter) in filters.iter().enumerate() {
+            if matches!(filter, Filter::Date(_)) && (idx != 0 || !param.is_time()) {
+                bail!(
+                    "The `date` filter can only be used first on a create or modify time in `{{:{}}}`",
+                    value
+                );
+            }
+        }
+
+        let escape = if !is_output
+            || filters.iter().any(|filter| matches!(filter, Filter::Raw))
+            || (param.is_html() && filters.iter().all(Filter::keeps_html))
+        {
+            Escape::None
+        } else if !self.in_script {
+            Escape::Html
+        } else if matches!(filters.last(), Some(Filter::Json)) {
+            Escape::None
+        } else {
+            Escape::Script
+        };
+
+        if filters.is_empty() && escape == Escape::None {
+            return Ok(param);
+        }
+
+        Ok(Part::Filtered {
+            value: Box::new(param),
+            filters,
+            escape,
+        })
+    }
+
+    fn parse_param(&self, param: &str) -> Result<Part> {
+        if let Some((var, field)) = param.split_once('.') {
+            if let Some(depth) = self.loop_vars.iter().position(|loop_var| loop_var == var) {
+                return match ItemParameter::parse(field) {
+                    Some(param) => Ok(Part::Item { depth, param }),
+                    None => Err(self.unknown_param(param)),
+                };
+            }
+        }
+
+        (self.param_matcher)(&format!("{{:{}}}", param)).map_err(|_| self.unknown_param(param))
+    }
+
+    fn unknown_param(&self, param: &str) -> Error {
+        if PARAMETER_NAMES.contains(&param) {
+            return anyhow!("`{{:{}}}` can't be used in this file", param);
+        }
+
+        let item_names = self.loop_vars.iter().flat_map(|var| {
+            ItemParameter::NAMES
+                .iter()
+                .map(move |field| format!("{}.{}", var, field))
+        });
+        let lowercase = param.to_lowercase();
+
+        let suggestion = PARAMETER_NAMES
+            .iter()
+            .map(|name| name.to_string())
+            .chain(item_names)
+            .map(|name| (edit_distance(&lowercase, &name), name))
+            .filter(|(distance, _)| *distance <= (param.len() / 3).max(1))
+            .min_by_key(|(distance, _)| *distance);
+
+        match suggestion {
+            Some((_, name)) => anyhow!(
+                "Unknown parameter: {{:{}}}, did you mean `{{:{}}}`?",
+                param,
+                name
+            ),
+            None => anyhow!("Unknown parameter: {{:{}}}", param),
+        }
+    }
+
+    fn error(&mut self, src: &Source, offset: usize, err: Error) {
+        let message = match self.includes.is_empty() {
+            true => err.to_string(),
+            false => format!("{} (included from {})", err, self.file),
+        };
+
+        self.errors
+            .push(TemplateError::new(src.file, src.text, offset, message));
+    }
+
+    fn update_in_script(&mut self, text: &str) {
+        let text = text.to_ascii_lowercase();
+
+        match (text.rfind("<script"), text.rfind("</script")) {
+            (Some(open), Some(close)) => self.in_script = open > close,
+            (Some(_), None) => self.in_script = true,
+            (None, Some(_)) => self.in_script = false,
+            (None, None) => {}
+        }
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+
+        for (j, cb) in b.iter().enumerate() {
+            let cur = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                prev
+            } else {
+                prev.min(row[j]).min(cur) + 1
+            };
+            prev = cur;
+        }
+    }
+
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{data_map::DocumentDataMap, tests::*};
+    use crate::database::{Facets, ListInfo, SearchQuery};
+
+    fn author_document<'d>(
+        req: &'d hyper::Request<hyper::Body>,
+        author: &'d str,
+    ) -> DocumentDataMap<'d> {
+        DocumentDataMap::from_author(req, author, ListInfo::new(1, 0, (0, 0), true))
+    }
+
+    #[test]
+    fn reflected_search_input_is_escaped() {
+        let req = request("/search?keyword=%3Cscript%3Ealert(1)%3C%2Fscript%3E&from=%22%3E%3Cb%3E");
+        let query = SearchQuery::from_uri_query(req.uri().query());
+        let document = DocumentDataMap::from_search(
+            &req,
+            &query,
+            &Facets::default(),
+            ListInfo::new(1, 0, (0, 0), true),
+        );
+
+        let html = render(
+            r#"<title>{:document.title}</title><a href="{:document.url}">{:document.breadcrumbs}</a>{:document.errors}"#,
+            &document,
+        );
+
+        assert!(!html.contains("<script>"), "{}", html);
+        assert!(!html.contains("<b>"), "{}", html);
+        assert!(
+            html.contains("Keyword: &lt;script&gt;alert(1)&lt;/script&gt;"),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains("Invalid `from` date `&quot;&gt;&lt;b&gt;`"),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn values_are_escaped_by_default() {
+        let req = request("/author");
+        let document = author_document(&req, r#"<script>"O'Hara" & co</script>"#);
+
+        assert_eq!(
+            render("{:document.title}", &document),
+            "Author: &lt;script&gt;&quot;O&#39;Hara&quot; &amp; co&lt;/script&gt;"
+        );
+        assert_eq!(
+            render("{:document.title|raw} {:document.title|safe}", &document),
+            r#"Author: <script>"O'Hara" & co</script> Author: <script>"O'Hara" & co</script>"#
+        );
+    }
+
+    #[test]
+    fn values_in_scripts_are_escaped_for_javascript() {
+        let req = request("/author");
+        let document = author_document(&req, "</script><b>\"a\"\n");
+
+        assert_eq!(
+            render(
+                r#"<script>var a = "{:document.title}", b = {:document.title|json};</script>{:document.title}"#,
+                &document
+            ),
+            concat!(
+                r#"<script>var a = "Author: \u003c/script\u003e\u003cb\u003e\"a\"\n", "#,
+                r#"b = "Author: \u003c/script\u003e\u003cb\u003e\"a\"\n";</script>"#,
+                "Author: &lt;/script&gt;&lt;b&gt;&quot;a&quot;\n"
+            )
+        );
+    }
+
+    #[test]
+    fn html_values_are_not_escaped_twice() {
+        let req = request("/author");
+        let document = author_document(&req, "<i>&</i>");
+
+        assert_eq!(
+            render("{:document.breadcrumbs}", &document),
+            "<span>Author: &lt;i&gt;&amp;&lt;/i&gt;</span>"
+        );
+        assert_eq!(
+            render("{:document.breadcrumbs|strip_html}", &document),
+            "Author: &lt;i&gt;&amp;&lt;/i&gt;"
+        );
+    }
+
+    #[test]
+    fn filters_are_applied_before_escaping() {
+        let req = request("/author");
+        let document = author_document(&req, "Jane & <Joe>");
+
+        assert_eq!(
+            render("{:document.title|truncate:10}", &document),
+            "Author: Ja…"
+        );
+        assert_eq!(
+            render("{:document.title|upper}", &document),
+            "AUTHOR: JANE &amp; &lt;JOE&gt;"
+        );
+        assert_eq!(
+            render("{:document.title|urlencode}", &document),
+            "Author%3A+Jane+%26+%3CJoe%3E"
+        );
+        assert_eq!(
+            render("{:document.title|json}", &document),
+            "&quot;Author: Jane \\u0026 \\u003cJoe\\u003e&quot;"
+        );
+
+        let index = DocumentDataMap::from_index(&req, ListInfo::new(1, 0, (0, 0), true));
+        assert_eq!(
+            render("{:document.title|default:<Home>}", &index),
+            "&lt;Home&gt;"
+        );
+    }
+
+    #[test]
+    fn invalid_filters() {
+        for template in [
+            "{:document.title|nope}",
+            "{:document.title|truncate}",
+            "{:document.title|truncate:x}",
+            "{:document.title|raw:1}",
+            "{:document.title|date:%Y}",
+        ] {
+            assert!(parse(template, &[]).is_err(), "{}", template);
+        }
+    }
+
+    fn error_messages(template: &str, partials: &[(&str, &str)]) -> Vec<String> {
+        parse(template, partials)
+            .unwrap_err()
+            .to_string()
+            .lines()
+            .map(ToOwned::to_owned)
+            .collect()
+    }
+
+    #[test]
+    fn nested_conditions() {
+        let req = request("/author");
+        let author = author_document(&req, "Jane");
+        let index = DocumentDataMap::from_index(&req, ListInfo::new(1, 0, (0, 0), true));
+        let template = concat!(
+            "{:if document.title}A{:if site.name}B{:else}C{:endif}",
+            "{:else}D{:if document.title}E{:else}F{:endif}{:endif}"
+        );
+
+        assert_eq!(render(template, &author), "AB");
+        assert_eq!(render(template, &index), "DF");
+    }
+
+    #[test]
+    fn nested_loops() {
+        let req = request("/author");
+        let document = DocumentDataMap::from_author(
+            &req,
+            "Jane",
+            ListInfo::new(2, 7, ("/author".len(), "/author".len()), true),
+        );
+
+        assert_eq!(
+            render(
+                concat!(
+                    "{:for item in document.page_nav}",
+                    "{:if item.is_current}[{:item.title}]{:else}{:item.title}{:endif}",
+                    "({:for other in document.page_nav}{:item.title}{:other.title}{:endfor})",
+                    "{:endfor}"
+                ),
+                &document
+            ),
+            "1(1112)[2](2122)"
+        );
+    }
+
+    #[test]
+    fn unclosed_blocks() {
+        assert_eq!(
+            error_messages("{:if document.title}a", &[]),
+            ["test.html:1:1: `{:if document.title}` is not closed by `{:endif}`"]
+        );
+        assert_eq!(
+            error_messages("a\n{:for item in document.page_nav}{:if item.link}", &[]),
+            [
+                "test.html:2:33: `{:if item.link}` is not closed by `{:endif}`",
+                "test.html:2:1: `{:for item in document.page_nav}` is not closed by `{:endfor}`"
+            ]
+        );
+        assert_eq!(
+            error_messages("{:for item in document.page_nav}{:endif}", &[]),
+            [
+                "test.html:1:1: `{:for item in document.page_nav}` is not closed by `{:endfor}`",
+                "test.html:1:33: Unexpected `{:endif}`"
+            ]
+        );
+    }
+
+    #[test]
+    fn unexpected_closing_tags() {
+        assert_eq!(
+            error_messages("a{:else}b{:endif}{:endfor}", &[]),
+            [
+                "test.html:1:2: Unexpected `{:else}`",
+                "test.html:1:10: Unexpected `{:endif}`",
+                "test.html:1:18: Unexpected `{:endfor}`"
+            ]
+        );
+        assert_eq!(
+            error_messages("{:for item in document.page_nav}{:else}{:endfor}", &[]),
+            [
+                "test.html:1:1: `{:for item in document.page_nav}` is not closed by `{:endfor}`",
+                "test.html:1:33: Unexpected `{:else}`",
+                "test.html:1:40: Unexpected `{:endfor}`"
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_loops() {
+        for template in [
+            "{:for page document.page_nav}{:endfor}",
+            "{:for Item in document.page_nav}{:endfor}",
+            "{:for site in document.page_nav}{:endfor}",
+            "{:for page in document.page_nav}{:endfor}",
+            "{:for item in document.title}{:endfor}",
+            "{:for item in document.page_nav}{:for item in document.page_nav}{:endfor}{:endfor}",
+        ] {
+            assert!(parse(template, &[]).is_err(), "{}", template);
+        }
+
+        assert!(parse(
+            "{:for item in document.page_nav}{:endfor}{:item.title}",
+            &[]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn includes() {
+        let req = request("/author");
+        let document = author_document(&req, "Jane");
+        let params = crate::database::Params::default();
+        let site = super::super::SiteDataMap::for_test(&params);
+        let parts = parse(
+            "<{:include outer}>",
+            &[
+                ("outer", "{:if document.title}{:include inner}{:endif}"),
+                ("inner", "{:document.title}"),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            super::super::Scope::new(&site, &document).render(&parts),
+            "<Author: Jane>"
+        );
+    }
+
+    #[test]
+    fn invalid_includes() {
+        assert_eq!(
+            error_messages("{:include nope}", &[]),
+            ["test.html:1:1: Unknown partial: nope"]
+        );
+        assert_eq!(
+            error_messages("{:include a}", &[("a", "x{:include a}")]),
+            ["partials/a:1:2: Partial `a` includes itself (included from test.html)"]
+        );
+        assert_eq!(
+            error_messages(
+                "{:include a}",
+                &[("a", "{:include b}"), ("b", "\n{:include a}")]
+            ),
+            ["partials/b:2:1: Partial `a` includes itself (included from test.html)"]
+        );
+        assert_eq!(
+            error_messages("{:include a}", &[("a", "{:if document.title}")]),
+            ["partials/a:1:1: `{:if document.title}` is not closed by `{:endif}` (included from test.html)"]
+        );
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(super::edit_distance("", ""), 0);
+        assert_eq!(super::edit_distance("title", "title"), 0);
+        assert_eq!(super::edit_distance("titel", "title"), 2);
+        assert_eq!(super::edit_distance("post.titl", "post.title"), 1);
+        assert_eq!(super::edit_distance("kitten", "sitting"), 3);
+        assert_eq!(super::edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn unknown_parameters() {
+        assert_eq!(
+            error_messages("{:document.titel}", &[]),
+            ["test.html:1:1: Unknown parameter: {:document.titel}, did you mean `{:document.title}`?"]
+        );
+        assert_eq!(
+            error_messages("{:Site.Name}", &[]),
+            ["test.html:1:1: Unknown parameter: {:Site.Name}, did you mean `{:site.name}`?"]
+        );
+        assert_eq!(
+            error_messages(
+                "{:for entry in document.page_nav}{:entry.titl}{:endfor}",
+                &[]
+            ),
+            ["test.html:1:34: Unknown parameter: {:entry.titl}, did you mean `{:entry.title}`?"]
+        );
+        assert_eq!(
+            error_messages("{:nothing.like.this}", &[]),
+            ["test.html:1:1: Unknown parameter: {:nothing.like.this}"]
+        );
+        assert_eq!(
+            error_messages("{:post.title}", &[]),
+            ["test.html:1:1: `{:post.title}` can't be used in this file"]
+        );
+    }
+
+    #[test]
+    fn all_errors_are_reported() {
+        assert_eq!(
+            error_messages(
+                "{:document.titel}\n  {:if nope}{:endif}\n{:document.title|nope}{:else}",
+                &[]
+            ),
+            [
+                "test.html:1:1: Unknown parameter: {:document.titel}, did you mean `{:document.title}`?",
+                "test.html:2:3: Unknown parameter: {:nope}",
+                "test.html:3:1: Unknown filter: nope",
+                "test.html:3:23: Unexpected `{:else}`"
+            ]
+        );
+    }
+}
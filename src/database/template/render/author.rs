@@ -1,6 +1,6 @@
 use super::{
     data_map::{DocumentDataMap, SiteDataMap, SummaryDataMap},
-    Template,
+    ListKind, Template,
 };
 use crate::database::{Database, ListInfo, Post};
 use hyper::{Body, Request, Response};
@@ -14,7 +14,7 @@ impl Template {
         posts: Vec<&Post>,
         list_info: ListInfo,
     ) -> Response<Body> {
-        let site_data = SiteDataMap::from_config_and_db(db);
+        let site_data = SiteDataMap::from_config_and_db(db, req);
         let document_data = DocumentDataMap::from_author(req, author, list_info);
 
         let header = self.header(&site_data, &document_data);
@@ -22,10 +22,10 @@ impl Template {
             .iter()
             .map(|post| {
                 let summary_data = SummaryDataMap::from_post(post);
-                self.summary(&site_data, &document_data, &summary_data)
+                self.summary(ListKind::Author, &site_data, &document_data, &summary_data)
             })
             .collect::<String>();
-        let page_nav = self.page_nav(&site_data, &document_data);
+        let page_nav = self.page_nav(ListKind::Author, &site_data, &document_data);
         let footer = self.footer(&site_data, &document_data);
 
         Response::new(Body::from(format!(
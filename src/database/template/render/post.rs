@@ -2,18 +2,25 @@ use super::{
     data_map::{DocumentDataMap, PostDataMap, SiteDataMap},
     Template,
 };
-use crate::database::{Database, Post};
+use crate::database::Database;
 use hyper::{Body, Request, Response};
 
 impl Template {
-    pub fn render_post(&self, db: &Database, req: &Request<Body>, post: &Post) -> Response<Body> {
-        let site_data = SiteDataMap::from_config_and_db(db);
+    pub fn render_post(
+        &self,
+        db: &Database,
+        req: &Request<Body>,
+        post_id: usize,
+    ) -> Response<Body> {
+        let post = db.posts.get(post_id);
+
+        let site_data = SiteDataMap::from_config_and_db(db, req);
         let document_data = DocumentDataMap::from_post(req, post);
 
-        let post_data = PostDataMap::from_post(post);
+        let post_data = PostDataMap::from_post(db, post_id);
 
         let header = self.header(&site_data, &document_data);
-        let post = self.post(&site_data, &document_data, &post_data);
+        let post = self.post(post, &site_data, &document_data, &post_data);
         let footer = self.footer(&site_data, &document_data);
 
         Response::new(Body::from(format!("{}{}{}", header, post, footer)))
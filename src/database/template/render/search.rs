@@ -1,8 +1,8 @@
 use super::{
     data_map::{DocumentDataMap, SiteDataMap, SummaryDataMap},
-    Template,
+    ListKind, Template,
 };
-use crate::database::{Database, ListInfo, Post, PostFilter};
+use crate::database::{Database, ListInfo, SearchQuery, SearchResult};
 use hyper::{Body, Request, Response};
 
 impl Template {
@@ -10,27 +10,33 @@ impl Template {
         &self,
         db: &Database,
         req: &Request<Body>,
-        filters: Vec<PostFilter>,
-        posts: Vec<&Post>,
+        query: SearchQuery,
+        result: SearchResult,
         list_info: ListInfo,
     ) -> Response<Body> {
-        let site_data = SiteDataMap::from_config_and_db(db);
-        let document_data = DocumentDataMap::from_search(req, filters, list_info);
+        let terms = query
+            .keywords()
+            .flat_map(|keyword| db.index.query_terms(keyword))
+            .collect();
+
+        let site_data = SiteDataMap::from_config_and_db(db, req);
+        let document_data = DocumentDataMap::from_search(req, &query, &result.facets, list_info);
 
         let header = self.header(&site_data, &document_data);
-        let posts = posts
-            .iter()
-            .map(|post| {
-                let summary_data = SummaryDataMap::from_post(post);
-                self.summary(&site_data, &document_data, &summary_data)
+        let contents = result
+            .contents
+            .into_iter()
+            .map(|content| {
+                let summary_data = SummaryDataMap::from_search_result(db, content, &terms);
+                self.summary(ListKind::Search, &site_data, &document_data, &summary_data)
             })
             .collect::<String>();
-        let page_nav = self.page_nav(&site_data, &document_data);
+        let page_nav = self.page_nav(ListKind::Search, &site_data, &document_data);
         let footer = self.footer(&site_data, &document_data);
 
         Response::new(Body::from(format!(
             "{}{}{}{}",
-            header, posts, page_nav, footer
+            header, contents, page_nav, footer
         )))
     }
 }
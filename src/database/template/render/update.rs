@@ -1,16 +1,33 @@
 use super::Template;
 use crate::database::DatabaseUpdateResult;
-use hyper::{Body, Response};
+use hyper::{header, Body, Response, StatusCode};
+use serde_json::json;
 
 impl Template {
     pub fn render_update(&self, result: DatabaseUpdateResult) -> Response<Body> {
-        match result {
-            DatabaseUpdateResult::Success => Response::new(Body::from("success")),
-            DatabaseUpdateResult::PermissionDenied => Response::builder()
-                .status(403)
-                .body(Body::from("permission denied"))
-                .unwrap(),
-            DatabaseUpdateResult::Error(error) => Response::new(Body::from(error.to_string())),
-        }
+        let (status, body) = match result {
+            DatabaseUpdateResult::Success(report) => (
+                StatusCode::OK,
+                json!({
+                    "status": "success",
+                    "template_error": report.template.map(|err| err.to_string()),
+                    "problems": report.lint.problems,
+                }),
+            ),
+            DatabaseUpdateResult::PermissionDenied => (
+                StatusCode::FORBIDDEN,
+                json!({ "status": "permission denied" }),
+            ),
+            DatabaseUpdateResult::Error(error) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                json!({ "status": "error", "error": error.to_string() }),
+            ),
+        };
+
+        Response::builder()
+            .status(status)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
     }
 }
@@ -0,0 +1,177 @@
+use super::html;
+use crate::database::text::Token;
+use std::{collections::HashSet, ops::Range};
+
+const WINDOW_TOKENS_COUNT: usize = 30;
+const WINDOW_LEADING_TOKENS_COUNT: usize = 6;
+const MAX_WINDOWS_COUNT: usize = 2;
+
+pub struct Snippet {
+    pub html: String,
+    pub match_count: usize,
+}
+
+impl Snippet {
+    pub fn generate(text: &str, tokens: &[Token], terms: &HashSet<String>) -> Self {
+        let matches = tokens
+            .iter()
+            .enumerate()
+            .filter(|(_, token)| terms.contains(&token.term))
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>();
+
+        let mut windows = Vec::new();
+
+        if matches.is_empty() {
+            windows.push(0..WINDOW_TOKENS_COUNT.min(tokens.len()));
+        } else {
+            let mut candidates = matches
+                .iter()
+                .map(|idx| {
+                    let start = idx.saturating_sub(WINDOW_LEADING_TOKENS_COUNT);
+                    let end = (start + WINDOW_TOKENS_COUNT).min(tokens.len());
+
+                    let in_window = &matches[matches.partition_point(|idx| *idx < start)
+                        ..matches.partition_point(|idx| *idx < end)];
+                    let distinct = in_window
+                        .iter()
+                        .map(|idx| tokens[*idx].term.as_str())
+                        .collect::<HashSet<_>>()
+                        .len();
+
+                    ((distinct, in_window.len()), start..end)
+                })
+                .collect::<Vec<_>>();
+
+            candidates
+                .sort_by(|(a_rank, a), (b_rank, b)| b_rank.cmp(a_rank).then(a.start.cmp(&b.start)));
+
+            for (_, window) in candidates {
+                if windows.len() == MAX_WINDOWS_COUNT {
+                    break;
+                }
+
+                if windows.iter().all(|other: &Range<usize>| {
+                    other.end <= window.start || window.end <= other.start
+                }) {
+                    windows.push(window);
+                }
+            }
+
+            windows.sort_by_key(|window| window.start);
+        }
+
+        let mut result = String::new();
+
+        for window in windows.iter().filter(|window| !window.is_empty()) {
+            if !result.is_empty() {
+                result.push_str(" … ");
+            } else if window.start > 0 {
+                result.push_str("… ");
+            }
+
+            Self::push_window(&mut result, text, tokens, window.clone(), terms);
+        }
+
+        if let Some(window) = windows.last() {
+            if window.end < tokens.len() {
+                result.push_str(" …");
+            }
+        }
+
+        Self {
+            html: result,
+            match_count: matches.len(),
+        }
+    }
+
+    fn push_window(
+        result: &mut String,
+        text: &str,
+        tokens: &[Token],
+        window: Range<usize>,
+        terms: &HashSet<String>,
+    ) {
+        let mut highlights: Vec<Range<usize>> = Vec::new();
+
+        for token in tokens[window.clone()]
+            .iter()
+            .filter(|token| terms.contains(&token.term))
+        {
+            match highlights.last_mut() {
+                Some(last) if token.span.start <= last.end => last.end = token.span.end,
+                _ => highlights.push(token.span.clone()),
+            }
+        }
+
+        let mut cursor = tokens[window.start].span.start;
+        let end = tokens[window.end - 1].span.end;
+
+        for highlight in highlights {
+            Self::push_text(result, &text[cursor..highlight.start]);
+            result.push_str("<mark>");
+            Self::push_text(result, &text[highlight.clone()]);
+            result.push_str("</mark>");
+            cursor = highlight.end;
+        }
+
+        Self::push_text(result, &text[cursor..end]);
+    }
+
+    // A snippet is shown inline, so line breaks of the text become spaces
+    fn push_text(result: &mut String, text: &str) {
+        html::push_escaped(result, &text.replace(['\r', '\n', '\t'], " "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{database::text::Tokenizer, Config};
+
+    fn snippet(text: &str, terms: &[&str]) -> Snippet {
+        Config::init_for_test();
+        let tokens = Tokenizer::new().tokenize_with_spans(text);
+        let terms = terms.iter().map(|term| term.to_string()).collect();
+
+        Snippet::generate(text, &tokens, &terms)
+    }
+
+    #[test]
+    fn highlights_matches() {
+        let result = snippet("Rust & <Go>: rust\nis fast", &["rust", "go"]);
+
+        assert_eq!(
+            result.html,
+            "<mark>Rust</mark> &amp; &lt;<mark>Go</mark>&gt;: <mark>rust</mark> is fast"
+        );
+        assert_eq!(result.match_count, 3);
+    }
+
+    #[test]
+    fn line_breaks_become_spaces() {
+        assert_eq!(
+            snippet("first\r\nsecond\tthird\n\nfourth", &["third"]).html,
+            "first  second <mark>third</mark>  fourth"
+        );
+    }
+
+    #[test]
+    fn windows() {
+        let text = (0..100).map(|idx| format!("w{}", idx)).collect::<Vec<_>>();
+        let result = snippet(&text.join(" "), &["w50", "w90"]);
+
+        assert_eq!(
+            result.html,
+            format!(
+                "… {} <mark>w50</mark> {} … {} <mark>w90</mark> {}",
+                text[44..50].join(" "),
+                text[51..74].join(" "),
+                text[84..90].join(" "),
+                text[91..100].join(" ")
+            )
+        );
+        assert_eq!(snippet("a b", &["c"]).html, "a b");
+        assert_eq!(snippet("", &["c"]).html, "");
+    }
+}
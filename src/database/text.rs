@@ -0,0 +1,217 @@
+use crate::Config;
+use rust_stemmers::Stemmer;
+use std::ops::Range;
+use unicode_normalization::{char::compose, UnicodeNormalization};
+
+pub struct Tokenizer {
+    stemmer: Option<Stemmer>,
+}
+
+impl Tokenizer {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            stemmer: Config::read().search.stemming.map(Stemmer::create),
+        }
+    }
+
+    pub fn tokenize(&self, text: &str) -> Vec<String> {
+        self.tokenize_with_spans(text)
+            .into_iter()
+            .map(|token| token.term)
+            .collect()
+    }
+
+    pub fn tokenize_with_spans(&self, text: &str) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        let mut word = (String::new(), 0..0);
+        let mut cjk_run: Vec<(char, Range<usize>)> = Vec::new();
+
+        for (idx, raw) in text.char_indices() {
+            let span = idx..idx + raw.len_utf8();
+
+            let chars = raw
+                .nfkd()
+                .filter(|c| !is_diacritic(*c))
+                .nfc()
+                .flat_map(char::to_lowercase);
+
+            for c in chars {
+                // Half-width and decomposed kana have their voicing marks as separate characters
+                if let ('\u{3099}' | '\u{309a}', Some((prev, prev_span))) = (c, cjk_run.last_mut())
+                {
+                    if let Some(composed) = compose(*prev, c) {
+                        *prev = composed;
+                        prev_span.end = span.end;
+                        continue;
+                    }
+                }
+
+                if is_cjk(c) {
+                    self.push_word(&mut tokens, &mut word);
+                    cjk_run.push((c, span.clone()));
+                } else if c.is_alphanumeric() {
+                    push_cjk_run(&mut tokens, &mut cjk_run);
+
+                    if word.0.is_empty() {
+                        word.1.start = span.start;
+                    }
+                    word.0.push(c);
+                    word.1.end = span.end;
+                } else {
+                    self.push_word(&mut tokens, &mut word);
+                    push_cjk_run(&mut tokens, &mut cjk_run);
+                }
+            }
+        }
+
+        self.push_word(&mut tokens, &mut word);
+        push_cjk_run(&mut tokens, &mut cjk_run);
+
+        tokens
+    }
+
+    fn push_word(&self, tokens: &mut Vec<Token>, word: &mut (String, Range<usize>)) {
+        if word.0.is_empty() {
+            return;
+        }
+
+        let term = match &self.stemmer {
+            Some(stemmer) => stemmer.stem(&word.0).into_owned(),
+            None => word.0.clone(),
+        };
+
+        tokens.push(Token {
+            term,
+            span: word.1.clone(),
+        });
+
+        word.0.clear();
+    }
+}
+
+pub struct Token {
+    pub term: String,
+    pub span: Range<usize>,
+}
+
+pub struct DocumentTokens {
+    pub title: Vec<String>,
+    pub body: Vec<String>,
+}
+
+impl DocumentTokens {
+    pub fn new(tokenizer: &Tokenizer, title: &str, body: &str) -> Self {
+        Self {
+            title: tokenizer.tokenize(title),
+            body: tokenizer.tokenize(body),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        self.title.iter().chain(self.body.iter())
+    }
+}
+
+// Han and kana characters are counted as a word each, Hangul is separated by spaces
+pub fn word_count(text: &str) -> usize {
+    let mut count = 0;
+    let mut in_word = false;
+
+    for c in text.chars() {
+        if is_cjk(c) && !('\u{ac00}'..='\u{d7af}').contains(&c) {
+            count += 1;
+            in_word = false;
+        } else if c.is_whitespace() {
+            in_word = false;
+        } else if !in_word && c.is_alphanumeric() {
+            count += 1;
+            in_word = true;
+        }
+    }
+
+    count
+}
+
+// CJK text has no word separators, so it is indexed as overlapping bigrams
+fn push_cjk_run(tokens: &mut Vec<Token>, cjk_run: &mut Vec<(char, Range<usize>)>) {
+    match cjk_run.len() {
+        0 => return,
+        1 => tokens.push(Token {
+            term: cjk_run[0].0.to_string(),
+            span: cjk_run[0].1.clone(),
+        }),
+        _ => tokens.extend(cjk_run.windows(2).map(|pair| Token {
+            term: pair.iter().map(|(c, _)| c).collect(),
+            span: pair[0].1.start..pair[1].1.end,
+        })),
+    }
+
+    cjk_run.clear();
+}
+
+// Only the diacritics of Latin, Greek and Cyrillic letters are folded, so kana keep their (han)dakuten
+fn is_diacritic(c: char) -> bool {
+    matches!(c, '\u{0300}'..='\u{036f}')
+}
+
+pub fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}'
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{ac00}'..='\u{d7af}'
+        | '\u{f900}'..='\u{faff}'
+        | '\u{20000}'..='\u{2fa1f}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokenize(text: &str) -> Vec<String> {
+        Config::init_for_test();
+        Tokenizer::new().tokenize(text)
+    }
+
+    #[test]
+    fn words() {
+        assert_eq!(
+            tokenize("Hello, wörld! Ça va?"),
+            ["hello", "world", "ca", "va"]
+        );
+        assert_eq!(tokenize("ＡＢＣ ﬁle"), ["abc", "file"]);
+    }
+
+    #[test]
+    fn cjk_bigrams() {
+        assert_eq!(tokenize("猫が好き"), ["猫が", "が好", "好き"]);
+        assert_eq!(tokenize("猫 rust犬"), ["猫", "rust", "犬"]);
+        assert_eq!(tokenize("한국어"), ["한국", "국어"]);
+    }
+
+    #[test]
+    fn kana_keep_voicing_marks() {
+        assert_eq!(tokenize("バグ ハク パン"), ["バグ", "ハク", "パン"]);
+        assert_eq!(tokenize("ﾊﾞｸﾞ ﾊﾟﾝ"), ["バグ", "パン"]);
+        assert_eq!(tokenize("ハ\u{3099}ク\u{3099}"), ["バグ"]);
+    }
+
+    #[test]
+    fn spans() {
+        let text = "Ünïcode 猫が";
+        let spans = Tokenizer::new()
+            .tokenize_with_spans(text)
+            .into_iter()
+            .map(|token| &text[token.span])
+            .collect::<Vec<_>>();
+
+        assert_eq!(spans, ["Ünïcode", "猫が"]);
+    }
+
+    #[test]
+    fn count_words() {
+        assert_eq!(word_count("Hello, world! 猫が好き 한국어 입니다"), 8);
+    }
+}
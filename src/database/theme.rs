@@ -0,0 +1,43 @@
+use super::Repo;
+use crate::Config;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+pub enum Theme {
+    Local(PathBuf),
+    Git(Repo),
+}
+
+impl Theme {
+    pub fn init() -> Result<Option<Self>> {
+        let theme = match &Config::read().theme {
+            Some(theme) => theme,
+            None => return Ok(None),
+        };
+
+        match (&theme.path, &theme.git) {
+            (Some(path), _) => Ok(Some(Self::Local(path.to_owned()))),
+            (None, Some(git)) => {
+                let mut repo = Repo::open_remote(git)?;
+                repo.fetch()?;
+                Ok(Some(Self::Git(repo)))
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+
+    pub fn fetch(&mut self) -> Result<()> {
+        if let Self::Git(repo) = self {
+            repo.fetch()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Local(path) => path,
+            Self::Git(repo) => repo.tempdir.path(),
+        }
+    }
+}
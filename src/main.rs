@@ -1,7 +1,7 @@
-use std::env;
+use std::{env, process};
 
 pub use crate::{
-    config::{Config, ConfigBuilder},
+    config::{Command, Config, ConfigBuilder},
     database::DatabaseManager,
     router::RouteTable,
 };
@@ -17,13 +17,13 @@ async fn main() {
 
     let mut cfg_builder = ConfigBuilder::new();
 
-    match cfg_builder.parse(&args) {
-        Ok(_) => {}
+    let command = match cfg_builder.parse(&args) {
+        Ok(command) => command,
         Err(err) => {
             eprintln!("{}\n\n{}", err, cfg_builder.get_usage());
             return;
         }
-    }
+    };
 
     match RouteTable::init() {
         Ok(_) => {}
@@ -41,6 +41,31 @@ async fn main() {
         }
     };
 
+    let db = DatabaseManager::read().await;
+
+    if let Command::Lint { json } = command {
+        let report = db.lint();
+
+        if json {
+            println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        } else {
+            report
+                .problems
+                .iter()
+                .for_each(|problem| println!("{}", problem));
+        }
+
+        if !report.problems.is_empty() {
+            process::exit(1);
+        }
+        return;
+    }
+
+    db.problems
+        .iter()
+        .for_each(|problem| eprintln!("warning: {}", problem));
+    drop(db);
+
     match server::start().await {
         Ok(_) => {}
         Err(err) => {
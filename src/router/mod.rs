@@ -1,10 +1,10 @@
-use crate::{Config, DatabaseManager};
+use crate::{database::format, Config, DatabaseManager};
 use anyhow::{anyhow, Result};
 use hyper::{Body, Request, Response, Uri};
 use hyper_staticfile::ResponseBuilder as StaticFileResponseBuilder;
 use matchit::{InsertError, Node};
 use once_cell::sync::OnceCell;
-use std::{collections::HashMap, convert::Infallible};
+use std::{collections::HashMap, convert::Infallible, path::Path};
 use tokio::sync::RwLock;
 
 mod archive;
@@ -76,26 +76,18 @@ impl RouteTable {
         Ok(not_found::handle(&req).await)
     }
 
-    pub async fn clear() {
+    pub async fn update(pages: HashMap<String, usize>, posts: HashMap<String, usize>) {
         let route_table = ROUTE_TABLE.get().unwrap();
-        route_table.map.clear().await;
-    }
-
-    pub async fn update_pages(pages: HashMap<String, usize>) {
-        let route_table = ROUTE_TABLE.get().unwrap();
-        route_table.map.update_pages(pages).await;
-    }
-
-    pub async fn update_posts(posts: HashMap<String, usize>) {
-        let route_table = ROUTE_TABLE.get().unwrap();
-        route_table.map.update_posts(posts).await;
+        route_table.map.update(pages, posts).await;
     }
 
     async fn serve_static(req: &Request<Body>) -> Option<Response<Body>> {
         let path = req.uri().path();
 
-        if path.starts_with("/pages/")
-            || path.starts_with("/posts/")
+        if ((path.starts_with("/pages/") || path.starts_with("/posts/"))
+            && format::from_path(Path::new(path)).is_some())
+            || path == "/menu.toml"
+            || path == "/site.toml"
             || path == "/template/header.html"
             || path == "/template/footer.html"
             || path == "/template/page_nav.html"
@@ -103,11 +95,48 @@ impl RouteTable {
             || path == "/template/post.html"
             || path == "/template/summary.html"
             || path == "/template/not_found.html"
+            || path.starts_with("/template/partials/")
+            || path.starts_with("/template/layouts/")
+            || path.starts_with("/template/shortcodes/")
+            || path.starts_with("/template/lists/")
         {
             return None;
         }
 
         let db = DatabaseManager::read().await;
+
+        if let (Some(theme), Some(theme_config)) = (&db.theme, &Config::read().theme) {
+            if let Some(asset_path) = path.strip_prefix(&theme_config.static_prefix) {
+                let root = theme.path().join("static");
+
+                if let Ok(result) = hyper_staticfile::resolve_path(root, asset_path).await {
+                    let res = StaticFileResponseBuilder::new()
+                        .request(req)
+                        .build(result)
+                        .unwrap();
+
+                    return Some(res);
+                }
+
+                return None;
+            }
+        }
+
+        if let Some(variant_path) = path.strip_prefix(&Config::read().images.prefix) {
+            let root = &Config::read().images.cache_dir;
+
+            if let Ok(result) = hyper_staticfile::resolve_path(root, variant_path).await {
+                let res = StaticFileResponseBuilder::new()
+                    .request(req)
+                    .build(result)
+                    .unwrap();
+
+                return Some(res);
+            }
+
+            return None;
+        }
+
         let root = db.repo.tempdir.path();
 
         if let Ok(result) = hyper_staticfile::resolve(root, req).await {
@@ -146,7 +175,7 @@ impl PathMap {
         }
     }
 
-    async fn clear(&self) {
+    async fn update(&self, pages: HashMap<String, usize>, posts: HashMap<String, usize>) {
         let mut map = self.map.write().await;
 
         map.clear();
@@ -160,20 +189,12 @@ impl PathMap {
             Config::read().url_patterns.search.to_owned(),
             RouteType::Search,
         );
-    }
-
-    async fn update_pages(&self, pages: HashMap<String, usize>) {
-        let mut map = self.map.write().await;
 
         map.extend(
             pages
                 .into_iter()
                 .map(|(path, id)| (path, RouteType::Page { id })),
         );
-    }
-
-    async fn update_posts(&self, posts: HashMap<String, usize>) {
-        let mut map = self.map.write().await;
 
         map.extend(
             posts
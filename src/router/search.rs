@@ -1,5 +1,5 @@
 use crate::{
-    database::{ListInfo, PostFilter},
+    database::{ListInfo, SearchQuery, SearchResult},
     router, DatabaseManager,
 };
 use hyper::{Body, Method, Request, Response};
@@ -9,25 +9,28 @@ pub async fn handle(req: &Request<Body>) -> Option<Response<Body>> {
     if req.method() == Method::GET {
         let db = DatabaseManager::read().await;
 
-        let query = req.uri().query()?;
-        let filters = PostFilter::from_uri_query(query)?;
+        let query = SearchQuery::from_uri_query(req.uri().query());
 
         let (current_page_num_in_list, page_num_pos_in_url, is_page_num_the_first_param_in_query) =
             router::get_page_num_and_pos_in_url(req.uri());
 
-        let (posts, total_num_of_articles_in_list) =
-            db.posts.search(&filters, current_page_num_in_list)?;
+        let result =
+            if query.errors.is_empty() && (!query.filters.is_empty() || query.sort.is_some()) {
+                db.search(&query, current_page_num_in_list)
+            } else {
+                SearchResult::default()
+            };
 
         let list_info = ListInfo::new(
             current_page_num_in_list,
-            total_num_of_articles_in_list,
+            result.total,
             page_num_pos_in_url,
             is_page_num_the_first_param_in_query,
         );
 
         let res = db
             .template
-            .render_search(db.deref(), req, filters, posts, list_info);
+            .render_search(db.deref(), req, query, result, list_info);
 
         return Some(res);
     }
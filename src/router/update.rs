@@ -32,12 +32,10 @@ pub async fn handle(req: &mut Request<Body>) -> Option<Response<Body>> {
     if update {
         let mut db = DatabaseManager::write().await;
 
-        let result = db
-            .update()
-            .await
-            .map_or_else(DatabaseUpdateResult::Error, |_| {
-                DatabaseUpdateResult::Success
-            });
+        let result = match db.update().await {
+            Ok(warnings) => DatabaseUpdateResult::Success(warnings),
+            Err(err) => DatabaseUpdateResult::Error(err),
+        };
         let res = db.template.render_update(result);
         return Some(res);
     }